@@ -12,11 +12,39 @@ enum InnerNumber {
 #[derive(Debug, Clone)]
 struct Number(InnerNumber, InnerNumber);
 
+/// When reducing a `Number`, pairs of regular numbers nested in at least `explode_depth`
+/// pairs explode (leftmost first), and regular numbers of at least `split_threshold` split.
+#[derive(Debug, Clone, Copy)]
+struct ReduceRules {
+	explode_depth: usize,
+	split_threshold: u64,
+}
+
+impl ReduceRules {
+	const STANDARD: ReduceRules = ReduceRules { explode_depth: 4, split_threshold: 10 };
+
+	/// Returns `None` if reducing could not end: if outermost pairs would explode
+	/// (`explode_depth` is 0), or if split regular numbers would split again
+	/// (`split_threshold` is less than 2).
+	#[allow(dead_code)]
+	fn new(explode_depth: usize, split_threshold: u64) -> Option<ReduceRules> {
+		if explode_depth < 1 || split_threshold < 2 { return None }
+		Some(ReduceRules { explode_depth, split_threshold })
+	}
+}
+
+/// A step taken while reducing a `Number`.
+#[derive(Debug, PartialEq, Eq)]
+enum ReduceAction {
+	/// `depth` is the number of pairs `pair` was nested in.
+	Explode { pair: (u64, u64), depth: usize },
+	Split(u64),
+}
+
 
-#[cfg(test)]
 mod formatting {
 	use std::fmt::Display;
-	use super::{InnerNumber, Number};
+	use super::{InnerNumber, Number, ReduceAction};
 
 	impl Display for InnerNumber {
 		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -34,6 +62,15 @@ mod formatting {
 		}
 	}
 
+	impl Display for ReduceAction {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				ReduceAction::Explode { pair: (l, r), depth } => write!(f, "explode [{l},{r}] at depth {depth}"),
+				ReduceAction::Split(r) => write!(f, "split {r}"),
+			}
+		}
+	}
+
 
 	#[test]
 	fn test() {
@@ -46,7 +83,7 @@ mod formatting {
 
 
 mod reducing {
-	use {super::InnerNumber::{self, *}, super::Number};
+	use {super::InnerNumber::{self, *}, super::{Number, ReduceAction, ReduceRules}};
 
 	enum _Exploded { False, True(Option<u64>, Option<u64>) }
 
@@ -72,10 +109,10 @@ mod reducing {
 			}
 		}
 
-		fn _explode(&mut self, depth: usize) -> _Exploded {
+		fn _explode(&mut self, depth: usize, rules: &ReduceRules) -> _Exploded {
 			match self {
 				Regular(_) => _Exploded::False,
-				Pair(number) => match number._explode(depth + 1) {
+				Pair(number) => match number._explode(depth + 1, rules) {
 					t @ _Exploded::True(Some(_), Some(_)) => {
 						*self = Regular(0);
 						t
@@ -85,9 +122,9 @@ mod reducing {
 			}
 		}
 
-		fn _split(&mut self) -> bool {
+		fn _split(&mut self, rules: &ReduceRules) -> bool {
 			match self {
-				Regular(ref val) if *val > 9 => {
+				Regular(ref val) if *val >= rules.split_threshold => {
 					let l = *val / 2;
 					let r = l + *val % 2;
 					let number = Number(Regular(l), Regular(r));
@@ -95,11 +132,26 @@ mod reducing {
 					true
 				}
 				Pair(number) => {
-					number._split()
+					number._split(rules)
 				}
 				_ => false
 			}
 		}
+
+		fn _first_exploding(&self, depth: usize, rules: &ReduceRules) -> Option<ReduceAction> {
+			match self {
+				Regular(_) => None,
+				Pair(number) => number._first_exploding(depth + 1, rules),
+			}
+		}
+
+		fn _first_splitting(&self, rules: &ReduceRules) -> Option<ReduceAction> {
+			match self {
+				Regular(val) if *val >= rules.split_threshold => Some(ReduceAction::Split(*val)),
+				Regular(_) => None,
+				Pair(number) => number._first_splitting(rules),
+			}
+		}
 	}
 
 	impl Number {
@@ -108,67 +160,124 @@ mod reducing {
 				._add_exploded(value, side)
 		}
 
-		fn _explode(&mut self, depth: usize) -> _Exploded {
-			if depth >= 4 {
-				match (&self.0, &self.1) {
-					(Regular(l), Regular(r)) => _Exploded::True(Some(*l), Some(*r)),
-					_ => unreachable!(),
+		fn _explode(&mut self, depth: usize, rules: &ReduceRules) -> _Exploded {
+			use _Exploded::*;
+			if depth >= rules.explode_depth {
+				if let (Regular(l), Regular(r)) = (&self.0, &self.1) { return True(Some(*l), Some(*r)) }
+			}
+			match self.0._explode(depth, rules) {
+				True(l, Some(r)) => {
+					self.1._add_exploded(r, _AddExplodedSide::Left);
+					True(l, None)
 				}
-			} else {
-				use _Exploded::*;
-				match self.0._explode(depth) {
-					True(l, Some(r)) => {
-						self.1._add_exploded(r, _AddExplodedSide::Left);
-						True(l, None)
-					}
-					t @ True(_, None) => t,
-					False => match self.1._explode(depth) {
-						True(Some(l), r) => {
-							self.0._add_exploded(l, _AddExplodedSide::Right);
-							True(None, r)
-						}
-						e => e
+				t @ True(_, None) => t,
+				False => match self.1._explode(depth, rules) {
+					True(Some(l), r) => {
+						self.0._add_exploded(l, _AddExplodedSide::Right);
+						True(None, r)
 					}
+					e => e
 				}
 			}
 		}
 
-		fn _split(&mut self) -> bool {
-			self.0._split() || self.1._split()
+		fn _split(&mut self, rules: &ReduceRules) -> bool {
+			self.0._split(rules) || self.1._split(rules)
 		}
 
-		pub(super) fn reduce(&mut self) {
+		pub(super) fn reduce(&mut self, rules: &ReduceRules) {
 			loop {
-				if self._explode(0).is_true() { continue }
-				if self._split() { continue }
+				if self._explode(0, rules).is_true() { continue }
+				if self._split(rules) { continue }
 				break
 			}
 		}
+
+		fn _first_exploding(&self, depth: usize, rules: &ReduceRules) -> Option<ReduceAction> {
+			if depth >= rules.explode_depth {
+				if let (Regular(l), Regular(r)) = (&self.0, &self.1) { return Some(ReduceAction::Explode { pair: (*l, *r), depth }) }
+			}
+			self.0._first_exploding(depth, rules).or_else(|| self.1._first_exploding(depth, rules))
+		}
+
+		fn _first_splitting(&self, rules: &ReduceRules) -> Option<ReduceAction> {
+			self.0._first_splitting(rules).or_else(|| self.1._first_splitting(rules))
+		}
+
+		/// Like `reduce`, but yielding each action taken, and the resulting intermediate number.
+		#[allow(dead_code)]
+		pub(super) fn reduction<'a>(&'a mut self, rules: &'a ReduceRules) -> impl Iterator<Item = (ReduceAction, Number)> + 'a {
+			std::iter::from_fn(|| {
+				let action = if let Some(action) = self._first_exploding(0, rules) {
+					assert!(self._explode(0, rules).is_true());
+					action
+				} else if let Some(action) = self._first_splitting(rules) {
+					assert!(self._split(rules));
+					action
+				} else {
+					return None
+				};
+				Some((action, self.clone()))
+			})
+		}
 	}
 
 
 	#[test]
 	fn test() -> Result<(), super::parsing::ParseNumberError>{
-		const REDUCED: fn(Number) -> Number = |mut n: Number| { n.reduce(); n };
+		const REDUCED: fn(Number) -> Number = |mut n: Number| { n.reduce(&ReduceRules::STANDARD); n };
 		assert_eq!(REDUCED("[[[[[9,8],1],2],3],4]".parse()?).to_string(), "[[[[0,9],2],3],4]");
 		assert_eq!(REDUCED("[7,[6,[5,[4,[3,2]]]]]".parse()?).to_string(), "[7,[6,[5,[7,0]]]]");
 		assert_eq!(REDUCED("[[6,[5,[4,[3,2]]]],1]".parse()?).to_string(), "[[6,[5,[7,0]]],3]");
 		assert_eq!(REDUCED("[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]".parse()?).to_string(), "[[3,[2,[8,0]]],[9,[5,[7,0]]]]");
+
+		let mut sum = Number(Pair(Box::new("[[[[4,3],4],4],[7,[[8,4],9]]]".parse()?)), Pair(Box::new("[1,1]".parse()?)));
+		assert_eq!(sum.reduction(&ReduceRules::STANDARD).map(|(a, n)| format!("{a}: {n}")).collect::<Vec<_>>(), [
+			"explode [4,3] at depth 4: [[[[0,7],4],[7,[[8,4],9]]],[1,1]]",
+			"explode [8,4] at depth 4: [[[[0,7],4],[15,[0,13]]],[1,1]]",
+			"split 15: [[[[0,7],4],[[7,8],[0,13]]],[1,1]]",
+			"split 13: [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]",
+			"explode [6,7] at depth 4: [[[[0,7],4],[[7,8],[6,0]]],[8,1]]",
+		]);
+		assert_eq!(sum.reduction(&ReduceRules::STANDARD).count(), 0);
+
+		let sum = |rules: &ReduceRules| -> Result<String, super::parsing::ParseNumberError> {
+			Ok("[[[[4,3],4],4],[7,[[8,4],9]]]".parse::<Number>()?.add_with("[1,1]".parse()?, rules).to_string())
+		};
+		assert_eq!(sum(&ReduceRules::STANDARD)?, "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+		assert_eq!(sum(&ReduceRules::new(4, 16).unwrap())?, "[[[[0,7],4],[15,[0,13]]],[1,1]]");
+		assert_eq!(sum(&ReduceRules::new(5, 10).unwrap())?, "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+		let rules = ReduceRules::new(3, 8).unwrap();
+		assert_eq!("[[1,[2,3]],4]".parse::<Number>()?.add_with("[5,[6,7]]".parse()?, &rules).to_string(), "[[[3,0],7],[5,[6,7]]]");
+
+		// Numbers nested deeper than the rules allow explode from the inside out
+		let rules = ReduceRules::new(3, 10).unwrap();
+		assert_eq!("[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]".parse::<Number>()?.add_with("[1,1]".parse()?, &rules).to_string(), "[[[6,0],[7,8]],[7,1]]");
+		assert_eq!(REDUCED("[[[[[[[1,2],3],4],5],6],7],8]".parse()?).to_string(), "[[[[7,0],[6,7]],7],8]");
+		assert_eq!(REDUCED("[[[[[1,[2,3]],4],5],6],7]".parse()?).to_string(), "[[[[0,7],5],6],7]");
+		assert!(ReduceRules::new(0, 10).is_none());
+		assert!(ReduceRules::new(4, 1).is_none());
 		Ok(())
 	}
 }
 
 
-impl Add for Number {
-	type Output = Number;
-	fn add(self, rhs: Self) -> Self::Output {
+impl Number {
+	fn add_with(self, rhs: Self, rules: &ReduceRules) -> Number {
 		use InnerNumber::*;
 		let mut res = Number(Pair(Box::new(self)), Pair(Box::new(rhs)));
-		res.reduce();
+		res.reduce(rules);
 		res
 	}
 }
 
+impl Add for Number {
+	type Output = Number;
+	fn add(self, rhs: Self) -> Self::Output {
+		self.add_with(rhs, &ReduceRules::STANDARD)
+	}
+}
+
 impl AddAssign for Number {
 	fn add_assign(&mut self, rhs: Self) {
 		use InnerNumber::Regular;
@@ -204,6 +313,164 @@ impl Number {
 }
 
 
+/// An alternative representation of `Number`, as a flat list of its
+/// regular numbers, each with the number of pairs it’s nested in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FlatNumber(Vec<(usize, u64)>);
+
+#[allow(dead_code)]
+mod flat {
+	use std::{fmt, ops::Add};
+	use super::{InnerNumber, Number, FlatNumber, ReduceRules};
+
+	impl From<&Number> for FlatNumber {
+		fn from(number: &Number) -> Self {
+			fn flatten(inner: &InnerNumber, depth: usize, flat: &mut Vec<(usize, u64)>) {
+				match inner {
+					InnerNumber::Regular(r) => flat.push((depth, *r)),
+					InnerNumber::Pair(n) => {
+						flatten(&n.0, depth + 1, flat);
+						flatten(&n.1, depth + 1, flat);
+					}
+				}
+			}
+
+			let mut flat = Vec::new();
+			flatten(&number.0, 1, &mut flat);
+			flatten(&number.1, 1, &mut flat);
+			FlatNumber(flat)
+		}
+	}
+
+	impl FlatNumber {
+		/// Folds pairs of regular numbers (i.e. adjacent ones of the same depth) up into
+		/// a single value using `pair`, assuming that `self` is well-formed.
+		fn folded<T>(&self, regular: impl Fn(u64) -> T, pair: impl Fn(T, T) -> T) -> T {
+			let mut stack = Vec::<(usize, T)>::with_capacity(self.0.len());
+			for &(depth, value) in &self.0 {
+				let mut top = (depth, regular(value));
+				while matches!(stack.last(), Some((d, _)) if *d == top.0) {
+					let (_, l) = stack.pop().unwrap();
+					top = (top.0 - 1, pair(l, top.1));
+				}
+				stack.push(top);
+			}
+			assert_eq!(stack.len(), 1);
+			stack.pop().unwrap().1
+		}
+
+		pub(super) fn magnitude(&self) -> u64 {
+			self.folded(|r| r, |l, r| 3 * l + 2 * r)
+		}
+
+		fn explode(&mut self, rules: &ReduceRules) -> bool {
+			// The first pair of equally deep regular numbers is the leftmost pair of regular numbers
+			let Some(i) = self.0.windows(2).position(|w| w[0].0 > rules.explode_depth && w[0].0 == w[1].0) else { return false };
+			let ((depth, l), (_, r)) = (self.0[i], self.0[i + 1]);
+			if let Some((_, prev)) = i.checked_sub(1).map(|p| &mut self.0[p]) { *prev += l }
+			if let Some((_, next)) = self.0.get_mut(i + 2) { *next += r }
+			self.0.splice(i..=i + 1, [(depth - 1, 0)]);
+			true
+		}
+
+		fn split(&mut self, rules: &ReduceRules) -> bool {
+			let Some(i) = self.0.iter().position(|&(_, value)| value >= rules.split_threshold) else { return false };
+			let (depth, value) = self.0[i];
+			self.0.splice(i..=i, [(depth + 1, value / 2), (depth + 1, value - value / 2)]);
+			true
+		}
+
+		pub(super) fn reduce(&mut self, rules: &ReduceRules) {
+			while self.explode(rules) || self.split(rules) {}
+		}
+	}
+
+	impl Add for FlatNumber {
+		type Output = FlatNumber;
+		fn add(mut self, rhs: Self) -> Self::Output {
+			self.0.extend(rhs.0);
+			for (depth, _) in &mut self.0 { *depth += 1 }
+			self.reduce(&ReduceRules::STANDARD);
+			self
+		}
+	}
+
+	impl Add for &FlatNumber {
+		type Output = FlatNumber;
+		fn add(self, rhs: Self) -> Self::Output {
+			let mut flat = Vec::with_capacity(self.0.len() + rhs.0.len());
+			flat.extend(self.0.iter().chain(&rhs.0).map(|&(depth, value)| (depth + 1, value)));
+			let mut sum = FlatNumber(flat);
+			sum.reduce(&ReduceRules::STANDARD);
+			sum
+		}
+	}
+
+	impl fmt::Display for FlatNumber {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			write!(f, "{}", self.folded(|r| r.to_string(), |l, r| format!("[{l},{r}]")))
+		}
+	}
+
+	/// Like `part2_impl`, but without cloning (only borrowing) the numbers.
+	pub(super) fn max_magnitude(numbers: &[FlatNumber]) -> u64 {
+		use itertools::Itertools;
+		numbers.iter()
+			.permutations(2)
+			.map(|pair| (pair[0] + pair[1]).magnitude())
+			.max()
+			.unwrap()
+	}
+
+
+	#[test]
+	fn test() -> Result<(), super::parsing::ParseNumberError> {
+		let flat = |s: &str| s.parse::<Number>().map(|n| FlatNumber::from(&n));
+		assert_eq!(flat("[[1,2],[[3,4],5]]")?, FlatNumber(vec![(2, 1), (2, 2), (3, 3), (3, 4), (2, 5)]));
+		assert_eq!(flat("[[1,2],[[3,4],5]]")?.to_string(), "[[1,2],[[3,4],5]]");
+		assert_eq!(flat("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]")?.magnitude(), 1384);
+
+		let reduced = |s: &str| flat(s).map(|mut n| { n.reduce(&ReduceRules::STANDARD); n.to_string() });
+		assert_eq!(reduced("[[[[[9,8],1],2],3],4]")?, "[[[[0,9],2],3],4]");
+		assert_eq!(reduced("[7,[6,[5,[4,[3,2]]]]]")?, "[7,[6,[5,[7,0]]]]");
+		assert_eq!(reduced("[[6,[5,[4,[3,2]]]],1]")?, "[[6,[5,[7,0]]],3]");
+		assert_eq!(reduced("[[3,[2,[1,[7,3]]]],[6,[5,[4,[3,2]]]]]")?, "[[3,[2,[8,0]]],[9,[5,[7,0]]]]");
+		assert_eq!((flat("[[[[4,3],4],4],[7,[[8,4],9]]]")? + flat("[1,1]")?).to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+		assert_eq!(reduced("[[[[[[[1,2],3],4],5],6],7],8]")?, "[[[[7,0],[6,7]],7],8]");
+		assert_eq!(reduced("[[[[[1,[2,3]],4],5],6],7]")?, "[[[[0,7],5],6],7]");
+
+		let numbers = super::input_numbers();
+		let flat_numbers = numbers.iter().map(FlatNumber::from).collect::<Vec<_>>();
+		let sum = flat_numbers.iter().skip(1).fold(flat_numbers[0].clone(), |l, r| &l + r);
+		assert_eq!(sum.to_string(), numbers.iter().cloned().sum::<Number>().to_string());
+		assert_eq!(sum.magnitude(), 4347);
+		assert_eq!(max_magnitude(&flat_numbers), 4721);
+		Ok(())
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn part2_benchmark() {
+		let numbers = super::input_numbers();
+		let flat_numbers = numbers.iter().map(FlatNumber::from).collect::<Vec<_>>();
+
+		let start = std::time::Instant::now();
+		let sequential_tree_max = super::max_magnitude_pair(&numbers, 1).unwrap().2;
+		println!("Tree numbers, on 1 thread: {:?}", start.elapsed());
+
+		let start = std::time::Instant::now();
+		let tree_max = super::part2_impl(numbers);
+		println!("Tree numbers, on all threads: {:?}", start.elapsed());
+		assert_eq!(sequential_tree_max, tree_max);
+
+		let start = std::time::Instant::now();
+		let flat_max = max_magnitude(&flat_numbers);
+		println!("Flat numbers: {:?}", start.elapsed());
+
+		assert_eq!(tree_max, flat_max);
+	}
+}
+
 fn input_numbers_from_str(s: &str) -> Vec<Number> {
 	parsing::numbers_from_str(s).unwrap()
 }
@@ -222,21 +489,30 @@ pub(crate) fn part1() -> u64 {
 }
 
 
-fn part2_impl(input_numbers: Vec<Number>) -> u64 {
-	let base = input_numbers.into_iter().enumerate();
-	let rev = base.clone().rev();
-
-	fn max_magnitude(input_iterator: impl Iterator<Item = (usize, Number)> + Clone) -> u64 {
-		use itertools::*;
-		input_iterator
-			.tuple_combinations()
-			.map(|((li, l), (ri, r))| (li, ri, l + r))
-			.map(|(_, _, sum)| sum.magnitude())
-			.max()
-			.unwrap()
-	}
+/// Returns the indices of the (ordered) pair of numbers whose sum has the largest magnitude, and
+/// that magnitude (preferring the lowest indices when tied), searching on `threads` threads.
+fn max_magnitude_pair(numbers: &[Number], threads: usize) -> Option<(usize, usize, u64)> {
+	use std::cmp::Reverse;
+	assert!(threads > 0);
+	let key = |&(l, r, magnitude): &(usize, usize, u64)| (magnitude, Reverse((l, r)));
+
+	std::thread::scope(|scope| {
+		let handles = (0..threads)
+			.map(|t| scope.spawn(move || (t..numbers.len())
+				.step_by(threads)
+				.flat_map(|l| (0..numbers.len()).filter(move |&r| r != l).map(move |r| (l, r)))
+				.map(|(l, r)| (l, r, (numbers[l].clone() + numbers[r].clone()).magnitude()))
+				.max_by_key(key)))
+			.collect::<Vec<_>>();
+		handles.into_iter()
+			.filter_map(|handle| handle.join().unwrap())
+			.max_by_key(key)
+	})
+}
 
-	max_magnitude(base).max(max_magnitude(rev))
+fn part2_impl(input_numbers: Vec<Number>) -> u64 {
+	let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+	max_magnitude_pair(&input_numbers, threads).unwrap().2
 }
 
 pub(crate) fn part2() -> u64 {
@@ -264,12 +540,20 @@ mod parsing {
 			use ParseNumberError::*;
 
 			let mut column = 1;
+			let skip_whitespace = |s: &mut &str, column: &mut usize| {
+				let trimmed = s.trim_start();
+				*column += s.len() - trimmed.len();
+				*s = trimmed;
+			};
+
+			skip_whitespace(&mut s, &mut column);
 			if !s.starts_with('[') {
 				return Err(InvalidFormat { column, found: s.chars().next() })
 			}
 
 			let mut state = vec![None];
 			loop {
+				skip_whitespace(&mut s, &mut column);
 				let inner = match state.last_mut() {
 					Some(found_pair @ None) => {
 						if s.starts_with('[') {
@@ -325,6 +609,7 @@ mod parsing {
 						}
 						None => return match inner {
 							InnerNumber::Pair(number) => {
+								skip_whitespace(&mut s, &mut column);
 								if let found @ Some(_) = s.chars().next() {
 									return Err(InvalidFormat { column, found })
 								}
@@ -366,6 +651,8 @@ mod parsing {
 		assert!(matches!("[1,2x".parse::<Number>(), Err(MissingPairClosingBracket { column: 5, found: Some('x') })));
 		assert!(matches!("[1,2]".parse::<Number>(), Ok(Number(Regular(1), Regular(2)))));
 		assert!(matches!("[1,2]x".parse::<Number>(), Err(InvalidFormat { column: 6, found: Some('x') })));
+		assert!(matches!(" [ 1 ,\t2 ] ".parse::<Number>(), Ok(Number(Regular(1), Regular(2)))));
+		assert!(matches!("[ 1 2]".parse::<Number>(), Err(MissingPairComma { column: 5, found: Some('2') })));
 		assert!(matches!("[[3,4],5]".parse::<Number>(), Ok(Number(Pair(num), Regular(5)))
 			if matches!(*num, Number(Regular(3), Regular(4)))));
 		assert!(matches!("[6,[7,8]]".parse::<Number>(), Ok(Number(Regular(6), Pair(num)))
@@ -374,6 +661,154 @@ mod parsing {
 }
 
 
+/// A JSON value as far as snailfish numbers are concerned (i.e. only non-negative integers and arrays).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum JsonValue {
+	Number(u64),
+	Array(Vec<JsonValue>),
+}
+
+#[allow(dead_code)]
+mod json {
+	use std::{fmt, num::ParseIntError, str::FromStr};
+	use super::{InnerNumber, Number, JsonValue};
+
+	impl From<&InnerNumber> for JsonValue {
+		fn from(inner: &InnerNumber) -> Self {
+			match inner {
+				InnerNumber::Regular(r) => JsonValue::Number(*r),
+				InnerNumber::Pair(n) => n.as_ref().into(),
+			}
+		}
+	}
+
+	impl From<&Number> for JsonValue {
+		fn from(number: &Number) -> Self {
+			JsonValue::Array(vec![(&number.0).into(), (&number.1).into()])
+		}
+	}
+
+	#[derive(Debug)]
+	pub(super) enum NumberFromJsonError {
+		RegularAtRoot,
+		/// Only arrays of two elements are pairs.
+		InvalidPairLength(usize),
+	}
+
+	impl TryFrom<&JsonValue> for InnerNumber {
+		type Error = NumberFromJsonError;
+		fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+			match value {
+				JsonValue::Number(r) => Ok(InnerNumber::Regular(*r)),
+				array => Ok(InnerNumber::Pair(Box::new(array.try_into()?))),
+			}
+		}
+	}
+
+	impl TryFrom<&JsonValue> for Number {
+		type Error = NumberFromJsonError;
+		fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+			match value {
+				JsonValue::Number(_) => Err(NumberFromJsonError::RegularAtRoot),
+				JsonValue::Array(elements) => match &elements[..] {
+					[l, r] => Ok(Number(l.try_into()?, r.try_into()?)),
+					_ => Err(NumberFromJsonError::InvalidPairLength(elements.len())),
+				}
+			}
+		}
+	}
+
+	impl fmt::Display for JsonValue {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			match self {
+				JsonValue::Number(n) => write!(f, "{n}"),
+				JsonValue::Array(elements) => {
+					write!(f, "[")?;
+					for (i, element) in elements.iter().enumerate() {
+						if i > 0 { write!(f, ",")? }
+						write!(f, "{element}")?;
+					}
+					write!(f, "]")
+				}
+			}
+		}
+	}
+
+	#[derive(Debug)]
+	pub(super) enum ParseJsonError {
+		InvalidFormat { column: usize, found: Option<char> },
+		InvalidNumber { column: usize, source: ParseIntError },
+	}
+
+	impl FromStr for JsonValue {
+		type Err = ParseJsonError;
+		fn from_str(s: &str) -> Result<Self, Self::Err> {
+			use ParseJsonError::*;
+
+			/// Reads a value at `s[*i..]` (preceded and followed by any whitespace), advancing `i` past it.
+			fn read(s: &str, i: &mut usize) -> Result<JsonValue, ParseJsonError> {
+				let skip_whitespace = |i: &mut usize| *i = s.len() - s[*i..].trim_start().len();
+				let invalid_format = |i: usize| InvalidFormat { column: i + 1, found: s[i..].chars().next() };
+
+				skip_whitespace(i);
+				let value = if s[*i..].starts_with('[') {
+					*i += 1;
+					skip_whitespace(i);
+					let mut elements = Vec::new();
+					if s[*i..].starts_with(']') {
+						*i += 1;
+					} else {
+						loop {
+							elements.push(read(s, i)?);
+							match s[*i..].chars().next() {
+								Some(',') => *i += 1,
+								Some(']') => { *i += 1; break }
+								_ => return Err(invalid_format(*i)),
+							}
+						}
+					}
+					JsonValue::Array(elements)
+				} else {
+					let end = *i + s[*i..].find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len() - *i);
+					if end == *i { return Err(invalid_format(*i)) }
+					let number = s[*i..end].parse().map_err(|e| InvalidNumber { column: *i + 1, source: e })?;
+					*i = end;
+					JsonValue::Number(number)
+				};
+				skip_whitespace(i);
+				Ok(value)
+			}
+
+			let mut i = 0;
+			let value = read(s, &mut i)?;
+			if i < s.len() { return Err(InvalidFormat { column: i + 1, found: s[i..].chars().next() }) }
+			Ok(value)
+		}
+	}
+
+
+	#[test]
+	fn test() -> Result<(), ParseJsonError> {
+		use {JsonValue::*, ParseJsonError::*};
+		assert_eq!("[1,[2,3]]".parse::<JsonValue>()?, Array(vec![Number(1), Array(vec![Number(2), Number(3)])]));
+		assert_eq!("\n[ 1 , [2,\n\t3] ]\n".parse::<JsonValue>()?.to_string(), "[1,[2,3]]");
+		assert_eq!("[]".parse::<JsonValue>()?, Array(vec![]));
+		assert!(matches!("[1,".parse::<JsonValue>(), Err(InvalidFormat { column: 4, found: None })));
+		assert!(matches!("[1 2]".parse::<JsonValue>(), Err(InvalidFormat { column: 4, found: Some('2') })));
+		assert!(matches!("[-1]".parse::<JsonValue>(), Err(InvalidFormat { column: 2, found: Some('-') })));
+		assert!(matches!("[1] x".parse::<JsonValue>(), Err(InvalidFormat { column: 5, found: Some('x') })));
+		assert!(matches!("[99999999999999999999]".parse::<JsonValue>(), Err(InvalidNumber { column: 2, .. })));
+
+		let json = "[[[0, [5, 8]], [[1, 7], [9, 6]]], [[4, [1, 2]], [[1, 4], 2]]]".parse::<JsonValue>()?;
+		let number = super::Number::try_from(&json).unwrap();
+		assert_eq!(number.to_string(), "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]");
+		assert_eq!(JsonValue::from(&number), json);
+		assert!(matches!(super::Number::try_from(&Number(1)), Err(NumberFromJsonError::RegularAtRoot)));
+		assert!(matches!(super::Number::try_from(&"[1,[2,3,4]]".parse()?), Err(NumberFromJsonError::InvalidPairLength(3))));
+		Ok(())
+	}
+}
+
 #[test]
 fn add() -> Result<(), parsing::ParseNumberError> {
 	assert_eq!(("[1,2]".parse::<Number>()? + "[[3,4],5]".parse::<Number>()?).to_string(),
@@ -451,6 +886,9 @@ fn tests() -> Result<(), parsing::ParseNumberError> {
 	assert_eq!(part1_impl(input_numbers_from_str(INPUT)), 4140);
 	assert_eq!(part1(), 4347);
 	assert_eq!(part2_impl(input_numbers_from_str(INPUT)), 3993);
+	for threads in [1, 3, 16] {
+		assert_eq!(max_magnitude_pair(&input_numbers_from_str(INPUT), threads), Some((8, 0, 3993)));
+	}
 	assert_eq!(part2(), 4721);
 	Ok(())
 }
@@ -7,11 +7,36 @@ struct EnhancementAlgorithm(Vec<bool>); // TODO(bm-w): Use more efficient storag
 
 use std::fmt::{Display, Write};
 
+#[derive(Clone)]
 struct Image {
 	bits: Vec<bool>,
 	stride: usize,
 }
 
+/// Like `Image`, but packing pixels into bits (row by row, with each row starting
+/// at a new word), tracking whether the (infinite) background is lit, and
+/// enhancing in-place by double buffering.
+struct BitImage {
+	width: usize,
+	height: usize,
+	words: Vec<u64>,
+	back_words: Vec<u64>,
+	background_lit: bool,
+}
+
+/// Offsets `[x, y]` of pixels around (and including) a pixel, in order of
+/// most to least significant bit of the index into a rule table.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+struct Neighborhood(Vec<[isize; 2]>);
+
+/// A 2D cellular automaton, looking up whether each pixel will be
+/// lit, by the pixels in its neighborhood, in a packed rule table.
+struct Automaton {
+	neighborhood: Neighborhood,
+	rules: Vec<u64>,
+}
+
 struct Map {
 	enhancement_algorithm: EnhancementAlgorithm,
 	input_image: Image,
@@ -85,6 +110,453 @@ impl Map {
 }
 
 
+#[allow(dead_code)]
+mod bitset {
+	use super::{BitImage, EnhancementAlgorithm, Image};
+
+	fn words_per_row(width: usize) -> usize {
+		width.div_ceil(u64::BITS as usize)
+	}
+
+	impl From<&Image> for BitImage {
+		fn from(image: &Image) -> Self {
+			let (width, height) = (image.stride, image.bits.len() / image.stride);
+			let mut words = vec![0; words_per_row(width) * height];
+			for (i, _) in image.bits.iter().enumerate().filter(|(_, &b)| b) {
+				let (x, y) = (i % width, i / width);
+				words[y * words_per_row(width) + x / 64] |= 1 << (x % 64);
+			}
+			BitImage { width, height, words, back_words: Vec::new(), background_lit: false }
+		}
+	}
+
+	impl BitImage {
+		/// Returns the `k`th word of row `y`, with pixels outside the image as the background.
+		fn row_word(&self, y: isize, k: usize) -> u64 {
+			let background = if self.background_lit { u64::MAX } else { 0 };
+			let stride = words_per_row(self.width);
+			if y < 0 || y as usize >= self.height || k >= stride { return background }
+			let word = self.words[y as usize * stride + k];
+			match self.width - k * 64 {
+				in_word @ 1..=63 => word | background << in_word,
+				_ => word,
+			}
+		}
+
+		/// Grows the image by one pixel on every side, as far as its background does not cover.
+		pub(super) fn enhance(&mut self, algorithm: &EnhancementAlgorithm) {
+			let (width, height) = (self.width + 2, self.height + 2);
+			let stride = words_per_row(width);
+			let mut back_words = std::mem::take(&mut self.back_words);
+			back_words.clear();
+			back_words.resize(stride * height, 0);
+
+			// Pixel `x` of the enhanced image is centered on pixel `x - 1` of the current image (and
+			// likewise for `y`), so it depends on up to pixel `x` (and rows `y - 2` through `y`).
+			for y in 0..height {
+				// A sliding window of the 3×3 pixels around `x` (as bits of the
+				// algorithm’s index), starting with the background to the left.
+				let mut index = if self.background_lit { 0b111_111_111 } else { 0 };
+				for k in 0..stride {
+					let [top, middle, bottom] = [2, 1, 0].map(|dy| self.row_word(y as isize - dy, k));
+					let mut enhanced_word = 0;
+					for bit in 0..(width - k * 64).min(64) {
+						index = (index << 1) & 0b110_110_110
+							| ((top >> bit & 1) << 6 | (middle >> bit & 1) << 3 | bottom >> bit & 1) as usize;
+						enhanced_word |= (algorithm.0[index] as u64) << bit;
+					}
+					back_words[y * stride + k] = enhanced_word;
+				}
+			}
+
+			self.back_words = std::mem::replace(&mut self.words, back_words);
+			(self.width, self.height) = (width, height);
+			self.background_lit = algorithm.0[if self.background_lit { super::ALGORITHM_BITS_LEN - 1 } else { 0 }];
+		}
+
+		/// Returns `None` if the (infinite) background is lit.
+		pub(super) fn count_lit_bits(&self) -> Option<usize> {
+			if self.background_lit { return None }
+			Some(self.words.iter().map(|w| w.count_ones() as usize).sum())
+		}
+	}
+
+
+	#[test]
+	fn test() {
+		let map = super::input_map_from_str(super::TEST_INPUT);
+		let mut image = BitImage::from(&map.input_image);
+		assert_eq!(image.count_lit_bits(), Some(10));
+		for _ in 0..2 { image.enhance(&map.enhancement_algorithm) }
+		assert_eq!(image.count_lit_bits(), Some(35));
+		for _ in 2..50 { image.enhance(&map.enhancement_algorithm) }
+		assert_eq!(image.count_lit_bits(), Some(3351));
+		assert_eq!((image.width, image.height), (115, 115));
+
+		let map = super::input_map();
+		let mut image = BitImage::from(&map.input_image);
+		image.enhance(&map.enhancement_algorithm);
+		assert_eq!(image.count_lit_bits(), None);
+		image.enhance(&map.enhancement_algorithm);
+		assert_eq!(image.count_lit_bits(), Some(5379));
+		for _ in 2..50 { image.enhance(&map.enhancement_algorithm) }
+		assert_eq!(image.count_lit_bits(), Some(17917));
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn enhance_benchmark() {
+		for steps in [50, 200] {
+			let start = std::time::Instant::now();
+			let mut map = super::input_map();
+			for _ in 0..steps { map = map.enhanced() }
+			let count = map.input_image.count_lit_bits();
+			println!("{steps} steps, `Image`: {:?}", start.elapsed());
+
+			let start = std::time::Instant::now();
+			let map = super::input_map();
+			let mut image = BitImage::from(&map.input_image);
+			for _ in 0..steps { image.enhance(&map.enhancement_algorithm) }
+			println!("{steps} steps, `BitImage`: {:?}", start.elapsed());
+
+			assert_eq!(image.count_lit_bits(), Some(count));
+		}
+	}
+}
+
+#[allow(dead_code)]
+mod netpbm {
+	use std::io::{self, Read, Write};
+	use super::{Image, Map};
+
+	impl Image {
+		/// Writes a raw (`P4`) PBM image; lit pixels are written as 1s (i.e. black).
+		pub(super) fn write_pbm(&self, out: &mut impl Write) -> io::Result<()> {
+			let (width, height) = (self.stride, self.bits.len() / self.stride);
+			write!(out, "P4\n{width} {height}\n")?;
+			for row in self.bits.chunks(width) {
+				let bytes = row.chunks(8)
+					.map(|byte| byte.iter().enumerate().fold(0u8, |acc, (i, &b)| acc | (b as u8) << (7 - i)))
+					.collect::<Vec<_>>();
+				out.write_all(&bytes)?;
+			}
+			Ok(())
+		}
+
+		/// Writes a raw (`P5`) PGM image; lit pixels are written as white (unlike in a PBM image).
+		pub(super) fn write_pgm(&self, out: &mut impl Write) -> io::Result<()> {
+			let (width, height) = (self.stride, self.bits.len() / self.stride);
+			write!(out, "P5\n{width} {height}\n255\n")?;
+			out.write_all(&self.bits.iter().map(|&b| if b { 255 } else { 0 }).collect::<Vec<_>>())
+		}
+	}
+
+	impl Map {
+		/// Writes the input image and the images of the next `steps` enhancements as a sequence of raw PBM
+		/// images (which a PBM file may contain). Note that a lit background can’t be represented.
+		pub(super) fn write_pbm_steps(mut self, steps: usize, out: &mut impl Write) -> io::Result<Map> {
+			self.input_image.write_pbm(out)?;
+			for _ in 0..steps {
+				self = self.enhanced();
+				self.input_image.write_pbm(out)?;
+			}
+			Ok(self)
+		}
+	}
+
+	#[derive(Debug)]
+	pub(super) enum PbmError {
+		Io(io::Error),
+		InvalidMagic,
+		/// `field` is the index of the width (0) or height (1).
+		InvalidHeader { field: usize },
+		InvalidPixel { index: usize, found: u8 },
+		MissingPixels { count: usize },
+	}
+
+	impl From<io::Error> for PbmError {
+		fn from(err: io::Error) -> Self {
+			PbmError::Io(err)
+		}
+	}
+
+	impl Image {
+		/// Reads a plain (`P1`) or raw (`P4`) PBM image (the first one, if there are more).
+		pub(super) fn read_pbm(input: &mut impl Read) -> Result<Image, PbmError> {
+			use PbmError::*;
+
+			let mut bytes = Vec::new();
+			input.read_to_end(&mut bytes)?;
+
+			let raw = match bytes.get(..2) {
+				Some(b"P1") => false,
+				Some(b"P4") => true,
+				_ => return Err(InvalidMagic),
+			};
+
+			// Skips whitespace and comments (from `#` through the end of the line)
+			let mut i = 2;
+			let skip_whitespace = |i: &mut usize| while let Some(&b) = bytes.get(*i) {
+				if b == b'#' {
+					while bytes.get(*i).is_some_and(|&b| b != b'\n') { *i += 1 }
+				} else if b.is_ascii_whitespace() {
+					*i += 1;
+				} else {
+					break
+				}
+			};
+			let mut dimensions = [0; 2];
+			for (field, dimension) in dimensions.iter_mut().enumerate() {
+				skip_whitespace(&mut i);
+				let end = i + bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+				*dimension = std::str::from_utf8(&bytes[i..end]).unwrap().parse::<usize>()
+					.ok().filter(|&d| d > 0)
+					.ok_or(InvalidHeader { field })?;
+				i = end;
+			}
+			let [width, height] = dimensions;
+			let len = width.checked_mul(height).ok_or(InvalidHeader { field: 1 })?;
+
+			// Not preallocating, as the header may claim more pixels than follow
+			let mut bits = Vec::new();
+			if raw {
+				// Exactly one whitespace character separates the header from the raster
+				i += 1;
+				let row_len = width.div_ceil(8);
+				for row in bytes.get(i..).unwrap_or_default().chunks(row_len).take(height) {
+					if row.len() < row_len { break }
+					bits.extend((0..width).map(|x| row[x / 8] & 1 << (7 - x % 8) != 0));
+				}
+			} else {
+				while bits.len() < len {
+					skip_whitespace(&mut i);
+					match bytes.get(i) {
+						Some(b'0') => bits.push(false),
+						Some(b'1') => bits.push(true),
+						Some(&found) => return Err(InvalidPixel { index: bits.len(), found }),
+						None => break,
+					}
+					i += 1;
+				}
+			}
+
+			if bits.len() < len { return Err(MissingPixels { count: len - bits.len() }) }
+			Ok(Image { bits, stride: width })
+		}
+	}
+
+
+	#[test]
+	fn test() {
+		use PbmError::*;
+
+		let map = super::input_map_from_str(super::TEST_INPUT);
+		let mut pbm = Vec::new();
+		map.input_image.write_pbm(&mut pbm).unwrap();
+		assert!(pbm.starts_with(b"P4\n15 15\n"));
+		assert_eq!(pbm.len(), 9 + 15 * 2);
+		let image = Image::read_pbm(&mut &pbm[..]).unwrap();
+		assert_eq!(image.to_string(), map.input_image.to_string());
+
+		let mut pgm = Vec::new();
+		map.input_image.write_pgm(&mut pgm).unwrap();
+		assert!(pgm.starts_with(b"P5\n15 15\n255\n"));
+		assert_eq!(pgm.iter().skip(14).filter(|&&b| b == 255).count(), 10);
+
+		let mut pbms = Vec::new();
+		let map = map.write_pbm_steps(2, &mut pbms).unwrap();
+		assert_eq!(map.input_image.count_lit_bits(), 35);
+		assert_eq!(pbms.windows(2).filter(|w| w == b"P4").count(), 3);
+
+		let image = Image::read_pbm(&mut &b"P1\n# A comment\n3 2\n0 1 0\n101\n"[..]).unwrap();
+		assert_eq!(image.to_string(), ".#.\n#.#\n");
+
+		assert!(matches!(Image::read_pbm(&mut &b"P2\n1 1\n0"[..]), Err(InvalidMagic)));
+		assert!(matches!(Image::read_pbm(&mut &b"P1\n1 x\n0"[..]), Err(InvalidHeader { field: 1 })));
+		assert!(matches!(Image::read_pbm(&mut &b"P1\n99999999999 99999999999\n0"[..]), Err(InvalidHeader { field: 1 })));
+		assert!(matches!(Image::read_pbm(&mut &b"P1\n2 1\n0 2"[..]), Err(InvalidPixel { index: 1, found: b'2' })));
+		assert!(matches!(Image::read_pbm(&mut &b"P1\n2 2\n0 1"[..]), Err(MissingPixels { count: 2 })));
+		assert!(matches!(Image::read_pbm(&mut &b"P4\n9 2\n\xff\x80"[..]), Err(MissingPixels { count: 9 })));
+	}
+}
+
+#[allow(dead_code)]
+mod automaton {
+	use std::{fs, io, path::Path, str::FromStr};
+	use super::{Automaton, EnhancementAlgorithm, Image, Neighborhood};
+
+	impl Neighborhood {
+		/// The square of pixels within `radius` (e.g. 3×3 for radius 1), in reading order.
+		pub(super) fn moore(radius: usize) -> Self {
+			let r = radius as isize;
+			Neighborhood((-r..=r).flat_map(|y| (-r..=r).map(move |x| [x, y])).collect())
+		}
+
+		/// The diamond of pixels within `radius` steps up, down, left or right, in reading order.
+		pub(super) fn von_neumann(radius: usize) -> Self {
+			let r = radius as isize;
+			Neighborhood(Neighborhood::moore(radius).0.into_iter()
+				.filter(|&[x, y]| x.abs() + y.abs() <= r)
+				.collect())
+		}
+
+		/// How far away from a pixel its neighborhood extends (and how far an image grows per step).
+		pub(super) fn radius(&self) -> usize {
+			self.0.iter().flatten().map(|o| o.unsigned_abs()).max().unwrap_or(0)
+		}
+	}
+
+	impl Automaton {
+		/// Panics if `rules` doesn’t hold a rule for each combination of pixels in `neighborhood`.
+		pub(super) fn new(neighborhood: Neighborhood, rules: impl IntoIterator<Item = bool>) -> Self {
+			let len = 1usize << neighborhood.0.len();
+			let mut packed = vec![0; len.div_ceil(64)];
+			let mut count = 0;
+			for (i, rule) in rules.into_iter().enumerate() {
+				assert!(i < len, "too many rules");
+				packed[i / 64] |= (rule as u64) << (i % 64);
+				count += 1;
+			}
+			assert_eq!(count, len, "too few rules");
+			Automaton { neighborhood, rules: packed }
+		}
+
+		fn rule(&self, index: usize) -> bool {
+			self.rules[index / 64] & 1 << (index % 64) != 0
+		}
+
+		/// Returns the image one step later (grown by the neighborhood’s radius on every
+		/// side), and whether the (infinite) background will be lit.
+		pub(super) fn stepped(&self, image: &Image, background_lit: bool) -> (Image, bool) {
+			let (width, height) = (image.stride as isize, (image.bits.len() / image.stride) as isize);
+			let r = self.neighborhood.radius() as isize;
+			let get = |x: isize, y: isize| if (0..width).contains(&x) && (0..height).contains(&y) {
+				image.bits[(y * width + x) as usize]
+			} else {
+				background_lit
+			};
+
+			let (stepped_width, stepped_height) = (width + 2 * r, height + 2 * r);
+			let bits = (0..stepped_height)
+				.flat_map(|y| (0..stepped_width).map(move |x| [x - r, y - r]))
+				.map(|[x, y]| self.neighborhood.0.iter()
+					.fold(0, |index, [dx, dy]| index << 1 | get(x + dx, y + dy) as usize))
+				.map(|index| self.rule(index))
+				.collect();
+
+			let background_index = if background_lit { (1 << self.neighborhood.0.len()) - 1 } else { 0 };
+			(Image { bits, stride: stepped_width as usize }, self.rule(background_index))
+		}
+
+		/// Returns `None` if the (infinite) background will be lit.
+		pub(super) fn lit_after(&self, image: &Image, steps: usize) -> Option<usize> {
+			let (mut image, mut background_lit) = (image.clone(), false);
+			for _ in 0..steps {
+				(image, background_lit) = self.stepped(&image, background_lit);
+			}
+			if background_lit { None } else { Some(image.count_lit_bits()) }
+		}
+
+		pub(super) fn load(path: impl AsRef<Path>) -> Result<Automaton, LoadAutomatonError> {
+			fs::read_to_string(path).map_err(LoadAutomatonError::Io)?
+				.parse().map_err(LoadAutomatonError::Invalid)
+		}
+	}
+
+	impl From<&EnhancementAlgorithm> for Automaton {
+		fn from(algorithm: &EnhancementAlgorithm) -> Self {
+			Automaton::new(Neighborhood::moore(1), algorithm.0.iter().copied())
+		}
+	}
+
+	#[allow(clippy::enum_variant_names)]
+	#[derive(Debug)]
+	pub(super) enum AutomatonError {
+		/// Expected `moore <radius>` or `von-neumann <radius>`.
+		InvalidNeighborhood,
+		InvalidRule { line: usize, column: usize, found: char },
+		InvalidRulesCount { expected: usize, found: usize },
+	}
+
+	#[derive(Debug)]
+	pub(super) enum LoadAutomatonError {
+		Io(io::Error),
+		Invalid(AutomatonError),
+	}
+
+	/// Expects the neighborhood on the first line, followed by the rules (as `#`
+	/// for lit and `.` for dark) spread over any number of lines.
+	impl FromStr for Automaton {
+		type Err = AutomatonError;
+		fn from_str(s: &str) -> Result<Self, Self::Err> {
+			use AutomatonError::*;
+			let mut lines = s.lines();
+
+			let neighborhood = match lines.next().map(|l| l.split_whitespace().collect::<Vec<_>>()).as_deref() {
+				Some(&[kind, radius]) => match (kind, radius.parse()) {
+					("moore", Ok(radius)) => Neighborhood::moore(radius),
+					("von-neumann", Ok(radius)) => Neighborhood::von_neumann(radius),
+					_ => return Err(InvalidNeighborhood),
+				}
+				_ => return Err(InvalidNeighborhood),
+			};
+			if neighborhood.0.len() >= usize::BITS as usize { return Err(InvalidNeighborhood) }
+
+			let rules = lines.enumerate()
+				.flat_map(|(l, line)| line.chars().enumerate().map(move |(c, chr)| (l + 2, c + 1, chr)))
+				.filter(|(_, _, chr)| !chr.is_whitespace())
+				.map(|(line, column, chr)| match chr {
+					'#' => Ok(true),
+					'.' => Ok(false),
+					found => Err(InvalidRule { line, column, found }),
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+
+			let expected = 1 << neighborhood.0.len();
+			if rules.len() != expected { return Err(InvalidRulesCount { expected, found: rules.len() }) }
+			Ok(Automaton::new(neighborhood, rules))
+		}
+	}
+
+
+	#[test]
+	fn test() {
+		use AutomatonError::*;
+
+		assert_eq!(Neighborhood::moore(1).0.len(), 9);
+		assert_eq!(Neighborhood::von_neumann(1), Neighborhood(vec![[0, -1], [-1, 0], [0, 0], [1, 0], [0, 1]]));
+		assert_eq!((Neighborhood::von_neumann(2).0.len(), Neighborhood::von_neumann(2).radius()), (13, 2));
+
+		// The puzzle’s enhancement is the 3×3 Moore neighborhood
+		let map = super::input_map_from_str(super::TEST_INPUT);
+		let automaton = Automaton::from(&map.enhancement_algorithm);
+		assert_eq!(automaton.lit_after(&map.input_image, 2), Some(35));
+		let map = super::input_map();
+		let automaton = Automaton::from(&map.enhancement_algorithm);
+		assert_eq!(automaton.lit_after(&map.input_image, 1), None);
+		assert_eq!(automaton.lit_after(&map.input_image, 2), Some(5379));
+
+		// Lit if any pixel in the neighborhood is lit grows a single pixel into a diamond
+		let pixel = Image { bits: vec![true], stride: 1 };
+		let automaton = "von-neumann 1\n.###############\n################".parse::<Automaton>().unwrap();
+		let (image, background_lit) = automaton.stepped(&automaton.stepped(&pixel, false).0, false);
+		assert!(!background_lit);
+		assert_eq!(image.to_string(), "..#..\n.###.\n#####\n.###.\n..#..\n");
+
+		// Lit if the center of a 5×5 neighborhood (i.e. the 13th of 25 bits) is lit keeps images as is
+		let automaton = Automaton::new(Neighborhood::moore(2), (0..1 << 25).map(|i| i & 1 << 12 != 0));
+		let (image, _) = automaton.stepped(&pixel, false);
+		assert_eq!((image.stride, image.count_lit_bits()), (5, 1));
+		assert!(image.bits[12]);
+
+		assert!(matches!("moore x\n".parse::<Automaton>(), Err(InvalidNeighborhood)));
+		assert!(matches!("hex 1\n".parse::<Automaton>(), Err(InvalidNeighborhood)));
+		assert!(matches!("von-neumann 1\n...\n..x".parse::<Automaton>(), Err(InvalidRule { line: 3, column: 3, found: 'x' })));
+		assert!(matches!("von-neumann 1\n...".parse::<Automaton>(), Err(InvalidRulesCount { expected: 32, found: 3 })));
+		assert!(matches!(Automaton::load("nonexistent/rules.txt"), Err(LoadAutomatonError::Io(_))));
+	}
+}
+
 mod parsing {
 	use std::{iter, str::FromStr};
 	use super::{EnhancementAlgorithm, Image, Map};
@@ -3,152 +3,450 @@
 
 #[derive(Debug)]
 struct Player {
-	#[allow(dead_code)]
 	id: usize,
 	/// 1-based
 	starting_position: usize,
 }
 
+/// The rules of a game of Dirac Dice.
+#[derive(Debug, Clone)]
+struct GameRules {
+	board_size: usize,
+	die_sides: usize,
+	rolls_per_turn: usize,
+	target_score: u64,
+}
+
+impl GameRules {
+	/// Part 1’s rules, to be played with a deterministic die.
+	const DETERMINISTIC: GameRules = GameRules { board_size: 10, die_sides: 100, rolls_per_turn: 3, target_score: 1000 };
+	/// Part 2’s rules, to be played with the Dirac die.
+	const QUANTUM: GameRules = GameRules { board_size: 10, die_sides: 3, rolls_per_turn: 3, target_score: 21 };
+}
+
+
+/// The outcome of a game with a deterministic die.
+#[derive(Debug, PartialEq, Eq)]
+struct DeterministicOutcome {
+	/// The index of the player that reached the target score.
+	winner: usize,
+	/// Per player, their final score.
+	scores: Vec<u64>,
+	/// The number of times the die was rolled.
+	rolls: usize,
+}
+
+/// The outcome of a game with the Dirac die.
+#[derive(Debug)]
+struct QuantumWins {
+	player_ids: Vec<usize>,
+	/// The number of universes that each turn splits a universe into.
+	turn_universes: usize,
+	/// Per player, per turn (of theirs), the number of universes in which they win.
+	player_turn_wins: Vec<Vec<u128>>,
+}
+
+/// Universes were to be counted beyond `u128::MAX`.
+#[derive(Debug, PartialEq, Eq)]
+struct UniversesOverflowError;
+
+/// A game could not be played (to its end).
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Eq)]
+enum GameError {
+	NoPlayers,
+	/// The player with `id` doesn’t start on the board.
+	InvalidStartingPosition { id: usize, position: usize },
+	UniversesOverflow,
+}
+
+impl From<UniversesOverflowError> for GameError {
+	fn from(_: UniversesOverflowError) -> Self {
+		GameError::UniversesOverflow
+	}
+}
+
+impl GameRules {
+	fn check_players(&self, players: &[Player]) -> Result<(), GameError> {
+		if players.is_empty() { return Err(GameError::NoPlayers) }
+		players.iter().try_for_each(|p| p.check_starting_position(self))
+	}
+}
+
+impl Player {
+	fn check_starting_position(&self, rules: &GameRules) -> Result<(), GameError> {
+		if (1..=rules.board_size).contains(&self.starting_position) { Ok(()) }
+		else { Err(GameError::InvalidStartingPosition { id: self.id, position: self.starting_position }) }
+	}
+}
+
 
-fn input_players_from_string(s: &str) -> [Player; 2] {
+fn input_players_from_string(s: &str) -> Vec<Player> {
 	parsing::players_from_str(s).unwrap()
 }
 
-fn input_players() -> [Player; 2] {
+fn input_players() -> Vec<Player> {
 	input_players_from_string(include_str!("day21.txt"))
 }
 
 
-fn part1_impl(input_players: [Player; 2]) -> u64 {
-	let mut rolls = (1..=100).cycle();
-	let mut positions = [
-		input_players[0].starting_position - 1,
-		input_players[1].starting_position - 1,
-	];
-	let mut scores = [0; 2];
+mod quantum_wins {
+	use std::io;
+	use super::QuantumWins;
+
+	#[allow(dead_code)]
+	impl QuantumWins {
+		pub(super) fn player_wins(&self, player: usize) -> u128 {
+			self.player_turn_wins[player].iter().sum()
+		}
+
+		/// The number of universes in which the game ends (i.e. in which any player wins).
+		pub(super) fn total_universes(&self) -> u128 {
+			(0..self.player_turn_wins.len()).map(|p| self.player_wins(p)).sum()
+		}
+
+		/// The probability of `player` winning on their `turn` (0-based). Universes in which
+		/// the game ends later are less likely, as they’ve been split by more turns.
+		pub(super) fn turn_win_probability(&self, player: usize, turn: usize) -> f64 {
+			let turns_played = turn * self.player_turn_wins.len() + player + 1;
+			self.player_turn_wins[player][turn] as f64 / (self.turn_universes as f64).powi(turns_played as i32)
+		}
+
+		pub(super) fn win_probability(&self, player: usize) -> f64 {
+			(0..self.player_turn_wins[player].len()).map(|n| self.turn_win_probability(player, n)).sum()
+		}
+
+		/// Writes a row with the number of universes in which, and the
+		/// probability that, a player wins on a turn, per player per turn.
+		pub(super) fn write_csv(&self, out: &mut impl io::Write) -> io::Result<()> {
+			writeln!(out, "turn,player,wins,probability")?;
+			for n in 0..self.player_turn_wins.iter().map(Vec::len).max().unwrap_or(0) {
+				for (p, turn_wins) in self.player_turn_wins.iter().enumerate() {
+					let Some(wins) = turn_wins.get(n) else { continue };
+					writeln!(out, "{},{},{wins},{}", n + 1, self.player_ids[p], self.turn_win_probability(p, n))?;
+				}
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test() {
+		let players = super::parsing::players_from_str("Player 1 starting position: 4\nPlayer 2 starting position: 8").unwrap();
+		let wins = super::quantum_wins(&players, &super::GameRules::QUANTUM).unwrap();
+		assert_eq!(wins.turn_universes, 27);
+		assert_eq!(wins.total_universes(), 444356092776315 + 341960390180808);
+		assert!((wins.win_probability(0) + wins.win_probability(1) - 1.0).abs() < 1e-9);
+		assert!(wins.win_probability(0) > wins.win_probability(1));
+
+		let mut csv = Vec::new();
+		wins.write_csv(&mut csv).unwrap();
+		let csv = String::from_utf8(csv).unwrap();
+		let mut lines = csv.lines();
+		assert_eq!(lines.next(), Some("turn,player,wins,probability"));
+		assert_eq!(lines.next(), Some("1,1,0,0"));
+		assert_eq!(lines.clone().count(), 2 * 10 - 1);
+		assert_eq!(lines.map(|l| l.split(',').nth(2).unwrap().parse::<u128>().unwrap()).sum::<u128>(), wins.total_universes());
+	}
+}
+
+
+fn deterministic_outcome(players: &[Player], rules: &GameRules) -> Result<DeterministicOutcome, GameError> {
+	rules.check_players(players)?;
+	let mut rolls = (1..=rules.die_sides).cycle();
+	let mut positions = players.iter().map(|p| p.starting_position - 1).collect::<Vec<_>>();
+	let mut scores = vec![0; players.len()];
 	for i in 0.. {
-		let i_mod = i % input_players.len();
-		let moove = rolls.by_ref().take(3).sum::<usize>();
+		let i_mod = i % players.len();
+		let moove = rolls.by_ref().take(rules.rolls_per_turn).sum::<usize>();
 		assert!(moove > 0);
-		let pos = (positions[i_mod] + moove) % 10;
+		let pos = (positions[i_mod] + moove) % rules.board_size;
 		positions[i_mod] = pos;
 		scores[i_mod] += pos as u64 + 1;
-		if scores[i_mod] >= 1000 {
-			let i_other = (i + 1) % input_players.len();
-			let score_other = scores[i_other];
-			return score_other * 3 * (i as u64 + 1)
+		if scores[i_mod] >= rules.target_score {
+			return Ok(DeterministicOutcome { winner: i_mod, scores, rolls: rules.rolls_per_turn * (i + 1) })
 		}
 	}
 	unreachable!()
 }
 
+/// Multiplies the score of the worst-scoring losing player by the number of times the die was rolled.
+fn part1_impl(input_players: &[Player], rules: &GameRules) -> Result<u64, GameError> {
+	let outcome = deterministic_outcome(input_players, rules)?;
+	let losing_score = outcome.scores.iter().enumerate()
+		.filter(|&(p, _)| p != outcome.winner)
+		.map(|(_, &s)| s)
+		.min().unwrap_or(0);
+	Ok(losing_score * outcome.rolls as u64)
+}
+
 pub(crate) fn part1() -> u64 {
-	part1_impl(input_players())
+	part1_impl(&input_players(), &GameRules::DETERMINISTIC).unwrap()
 }
 
 
 mod quantum {
-	use super::Player;
+	use itertools::Itertools;
+	use super::{Player, GameRules, GameError, UniversesOverflowError};
 
-	const TARGET_SCORE: u64 = 21;
+	fn checked_add(lhs: u128, rhs: u128) -> Result<u128, UniversesOverflowError> {
+		lhs.checked_add(rhs).ok_or(UniversesOverflowError)
+	}
 
-	/// In a player’s “worst-case” universe, where regardless of where
-	/// they start, they land on space 3 and get 3 point on the first
-	/// turn, then roll 9 to land on space 2, then roll 9 to land on
-	/// space 1, etc. for 3+2+1+4+2+1+4+2+1+4=24 points after 10 turns.
-	const NUM_TURNS: usize = 10;
+	impl GameRules {
+		/// Per number of spaces moved, the number of universes in which a
+		/// turn moves that many spaces. E.g. for 3 rolls of a 3-sided die,
+		/// there will be one universe where we moved 3 spaces (we rolled 3x1),
+		/// 3 universes where we moved 4 spaces (we rolled 2x1 & 1x3, in 3
+		/// permutations), etc.
+		pub(super) fn kernel(&self) -> Vec<usize> {
+			let mut kernel = vec![1];
+			for _ in 0..self.rolls_per_turn {
+				let mut new_kernel = vec![0; kernel.len() + self.die_sides];
+				for (moove, &universes) in kernel.iter().enumerate() {
+					for roll in 1..=self.die_sides {
+						new_kernel[moove + roll] += universes;
+					}
+				}
+				kernel = new_kernel;
+			}
+			kernel
+		}
 
-	/// After one turn (3 die rolls), there will be one universe where
-	/// we moved 3 spaces (we rolled 3x1), 3 universes where we moved 4
-	/// spaces (we rolled 2x1 & 1x3, in 3 permutations), etc.
-	const KERNEL: [usize; 10] = [0, 0, 0, 1, 3, 6, 7, 6, 3, 1];
+		/// The number of turns in a player’s “worst-case” universe, where
+		/// regardless of where they start, they move so that they score as
+		/// few points as possible. E.g. for part 2’s rules, they land on
+		/// space 3 and get 3 point on the first turn, then roll 9 to land on
+		/// space 2, then roll 9 to land on space 1, etc. for
+		/// 3+2+1+4+2+1+4+2+1+4=24 points after 10 turns.
+		pub(super) fn max_turns(&self) -> usize {
+			let moves = self.kernel().iter().positions(|&u| u > 0).collect::<Vec<_>>();
+
+			// Per space, the least score with which it can be reached.
+			let mut space_min_scores = vec![Some(0); self.board_size];
+			let mut turns = 0;
+			while space_min_scores.iter().flatten().any(|&s| s < self.target_score) {
+				let mut new_space_min_scores = vec![None::<u64>; self.board_size];
+				for (space, score) in space_min_scores.iter().enumerate() {
+					let Some(score) = score.filter(|&s| s < self.target_score) else { continue };
+					for moove in &moves {
+						let new_space = (space + moove) % self.board_size;
+						let new_score = score + new_space as u64 + 1;
+						let min_score = &mut new_space_min_scores[new_space];
+						*min_score = Some(min_score.map_or(new_score, |s| s.min(new_score)));
+					}
+				}
+				space_min_scores = new_space_min_scores;
+				turns += 1;
+			}
+			turns
+		}
+	}
 
 	#[derive(Debug, Clone, Copy)]
 	pub(super) struct Turn {
 		#[allow(dead_code)]
 		pub(super) seq: usize,
-		pub(super) still_playing_universes: usize,
-		pub(super) reached_target_universes: usize,
+		pub(super) still_playing_universes: u128,
+		pub(super) reached_target_universes: u128,
 	}
 
 	impl Player {
-		pub(super) fn quantum_turns(&self) -> impl Iterator<Item = Turn> + '_ {
+		pub(super) fn quantum_turns(&self, rules: &GameRules) -> Result<impl Iterator<Item = Result<Turn, UniversesOverflowError>>, GameError> {
+			self.check_starting_position(rules)?;
+			let (board_size, target_score) = (rules.board_size, rules.target_score);
+			let kernel = rules.kernel().into_iter().map(|u| u as u128).collect::<Vec<_>>();
+
 			// Was 1-based, need 0-based.
 			let starting_position = self.starting_position - 1;
 	
 			// For each space, all possible scores, and for each score,
 			// the number of universes in that space with that score. 
 			let mut space_score_universes
-				= [[0usize; TARGET_SCORE as usize]; 10];
+				= vec![vec![0u128; target_score as usize]; board_size];
 	
 			// Before the first turn, there is only one universe
 			// at the starting point and it has a score of zero.
 			space_score_universes[starting_position][0] = 1;
 
-			(0..NUM_TURNS).map(move |turn| {
+			Ok((0..rules.max_turns()).map(move |turn| {
 				let mut still_playing_universes = 0;
 				let mut reached_target_universes = 0;
 				let mut new_space_score_universes
-					= [[0usize; TARGET_SCORE as usize]; 10];
+					= vec![vec![0u128; target_score as usize]; board_size];
 				for (space, score_universes) in space_score_universes.iter().enumerate() {
 					for (score, universes) in score_universes.iter().enumerate().map(|(s, &u)| (s as u64, u)) {
 						if universes == 0 { continue }
-						for (moove, &move_universes) in KERNEL.iter().enumerate() {
+						for (moove, &move_universes) in kernel.iter().enumerate() {
 							if move_universes == 0 { continue }
-							let new_universes = universes * move_universes;
-							let new_space = (space + moove) % 10;
+							let new_universes = universes.checked_mul(move_universes).ok_or(UniversesOverflowError)?;
+							let new_space = (space + moove) % board_size;
 							let new_score = score + new_space as u64 + 1;
-							if new_score < TARGET_SCORE {
-								still_playing_universes += new_universes;
-								new_space_score_universes[new_space][new_score as usize] += new_universes;
+							if new_score < target_score {
+								still_playing_universes = checked_add(still_playing_universes, new_universes)?;
+								let space_score_universes = &mut new_space_score_universes[new_space][new_score as usize];
+								*space_score_universes = checked_add(*space_score_universes, new_universes)?;
 							} else {
-								reached_target_universes += new_universes;
+								reached_target_universes = checked_add(reached_target_universes, new_universes)?;
 							}
 						}
 					}
 				}
 				space_score_universes = new_space_score_universes;
-				Turn { seq: turn, still_playing_universes, reached_target_universes }
-			})
+				Ok(Turn { seq: turn, still_playing_universes, reached_target_universes })
+			}))
 		}
 	}
+
+	#[test]
+	fn test() {
+		assert_eq!(GameRules::QUANTUM.kernel(), [0, 0, 0, 1, 3, 6, 7, 6, 3, 1]);
+		assert_eq!(GameRules::QUANTUM.max_turns(), 10);
+		assert_eq!(GameRules { die_sides: 2, rolls_per_turn: 2, ..GameRules::QUANTUM }.kernel(), [0, 0, 1, 2, 1]);
+		assert_eq!(GameRules { target_score: 1, ..GameRules::QUANTUM }.max_turns(), 1);
+		assert_eq!(GameRules { board_size: 1, target_score: 5, ..GameRules::QUANTUM }.max_turns(), 5);
+	}
 }
 
 
-fn part2_impl(input_players: [Player; 2]) -> usize {
-	let turns = {
-		let mut turns = [
-			input_players[0].quantum_turns(),
-			input_players[1].quantum_turns()
-		];
-		let mut prev_turn = turns[0].next();
-		let mut t = 0;
-		std::iter::from_fn(move || {
-			let prev_t = t;
-			t = (t + 1) % turns.len();
-			let mut round_turns = [None, None];
-			round_turns[prev_t] = prev_turn;
-			round_turns[t] = turns[t].next();
-			prev_turn = round_turns[t];
-			round_turns[0].zip(round_turns[1])
-				.map(|(t0, t1)| (t, [t0, t1]))
-		})
-	};
+/// Per player, per turn (of theirs), the number of universes in which they win.
+fn quantum_wins(players: &[Player], rules: &GameRules) -> Result<QuantumWins, GameError> {
+	rules.check_players(players)?;
+	let turns = players.iter()
+		.map(|p| Ok(p.quantum_turns(rules)?.collect::<Result<Vec<_>, _>>()?))
+		.collect::<Result<Vec<_>, GameError>>()?;
+
+	// A player wins on their `n`th turn in every combination of their universes in which they reached the
+	// target, with the universes of players before them still playing after their `n`th turn, and the
+	// universes of players after them still playing after their `n - 1`th turn (there being one before).
+	let still_playing_universes = |p: usize, n: Option<usize>|
+		n.map_or(1, |n| turns[p][n].still_playing_universes);
+	let player_turn_wins = (0..players.len())
+		.map(|p| (0..turns[p].len())
+			.map(|n| (0..players.len())
+				.filter(|&o| o != p)
+				.map(|o| still_playing_universes(o, if o < p { Some(n) } else { n.checked_sub(1) }))
+				.try_fold(turns[p][n].reached_target_universes, u128::checked_mul)
+				.ok_or(GameError::UniversesOverflow))
+			.collect::<Result<Vec<_>, _>>())
+		.collect::<Result<Vec<_>, _>>()?;
+
+	// Making sure that summing wins (per player, and in total) can’t overflow either
+	player_turn_wins.iter().flatten().try_fold(0, |sum: u128, &w| sum.checked_add(w))
+		.ok_or(GameError::UniversesOverflow)?;
+
+	Ok(QuantumWins {
+		player_ids: players.iter().map(|p| p.id).collect(),
+		turn_universes: rules.kernel().into_iter().sum(),
+		player_turn_wins,
+	})
+}
+
+/// Per player, the number of universes in which they win.
+fn quantum_win_universes(players: &[Player], rules: &GameRules) -> Result<Vec<u128>, GameError> {
+	let wins = quantum_wins(players, rules)?;
+	Ok((0..players.len()).map(|p| wins.player_wins(p)).collect())
+}
 
-	let mut win_universes = [0, 0];
-	for (t, turns) in turns {
-		let reached_target_universes = turns[t].reached_target_universes;
-		let other_still_playing_universes = turns[(t + 1) % turns.len()].still_playing_universes;
-		win_universes[t] += reached_target_universes * other_still_playing_universes;
-	}
-	win_universes.into_iter().max().unwrap()
+fn part2_impl(input_players: &[Player], rules: &GameRules) -> Result<u128, GameError> {
+	Ok(quantum_win_universes(input_players, rules)?.into_iter().max().unwrap())
 }
 
-pub(crate) fn part2() -> usize {
-	part2_impl(input_players())
+pub(crate) fn part2() -> u128 {
+	part2_impl(&input_players(), &GameRules::QUANTUM).unwrap()
 }
 
 
+mod start_analysis {
+	use std::fmt;
+	use super::{Player, GameRules, GameError};
+
+	#[derive(Debug, PartialEq, Eq)]
+	pub(super) struct StartOutcome {
+		/// The index of the player that wins in the most universes.
+		pub(super) quantum_winner: usize,
+		/// Per player, the number of universes in which they win.
+		pub(super) quantum_win_universes: Vec<u128>,
+		/// `part1_impl`’s product of the losing score and the number of die rolls.
+		pub(super) deterministic_product: u64,
+	}
+
+	/// Per pair of starting positions, the outcome of both games (in `[player 1][player 2]` order, 0-based).
+	#[derive(Debug)]
+	pub(super) struct StartAnalysis(pub(super) Vec<Vec<StartOutcome>>);
+
+	#[allow(dead_code)]
+	impl StartAnalysis {
+		/// For 2 players, starting anywhere on the quantum rules’ board.
+		pub(super) fn new(deterministic_rules: &GameRules, quantum_rules: &GameRules) -> Result<Self, GameError> {
+			let board_size = quantum_rules.board_size;
+			(1..=board_size)
+				.map(|start1| (1..=board_size)
+					.map(|start2| {
+						let players = [
+							Player { id: 1, starting_position: start1 },
+							Player { id: 2, starting_position: start2 },
+						];
+						let quantum_win_universes = super::quantum_win_universes(&players, quantum_rules)?;
+						Ok(StartOutcome {
+							quantum_winner: (0..players.len()).max_by_key(|&p| quantum_win_universes[p]).unwrap(),
+							quantum_win_universes,
+							deterministic_product: super::part1_impl(&players, deterministic_rules)?,
+						})
+					})
+					.collect())
+				.collect::<Result<_, _>>()
+				.map(StartAnalysis)
+		}
+	}
+
+	/// Renders a cell per pair of starting positions, with player 1’s starting position
+	/// down, and player 2’s across, as `<quantum winner’s id>:<deterministic product>`.
+	impl fmt::Display for StartAnalysis {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			let cells = self.0.iter()
+				.map(|row| row.iter()
+					.map(|o| format!("{}:{}", o.quantum_winner + 1, o.deterministic_product))
+					.collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+			let width = cells.iter().flatten().map(String::len).max().unwrap_or(0);
+			let row_width = self.0.len().to_string().len();
+
+			write!(f, "{:row_width$} |", "")?;
+			for start2 in 1..=self.0.len() {
+				write!(f, " {start2:>width$}")?;
+			}
+			for (start1, row) in cells.iter().enumerate() {
+				write!(f, "\n{:>row_width$} |", start1 + 1)?;
+				for cell in row {
+					write!(f, " {cell:>width$}")?;
+				}
+			}
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn test() {
+		let analysis = StartAnalysis::new(&GameRules::DETERMINISTIC, &GameRules::QUANTUM).unwrap();
+		assert_eq!(analysis.0[3][7], StartOutcome {
+			quantum_winner: 0,
+			quantum_win_universes: vec![444356092776315, 341960390180808],
+			deterministic_product: 739785,
+		});
+		assert_eq!(analysis.0[5][3].deterministic_product, 920580);
+		assert_eq!(analysis.0[5][3].quantum_win_universes.iter().max(), Some(&647920021341197));
+
+		let rendered = analysis.to_string();
+		let lines = rendered.lines().collect::<Vec<_>>();
+		assert_eq!(lines.len(), 11);
+		assert!(lines[0].starts_with("   |"));
+		assert!(lines[4].starts_with(" 4 |"));
+		assert_eq!(lines[4].split_whitespace().nth(2 + 7), Some("1:739785"));
+		assert!(lines.iter().all(|l| l.len() == lines[0].len()));
+	}
+}
+
 mod parsing {
 	use std::{str::FromStr, num::ParseIntError};
 	use super::Player;
@@ -196,7 +494,7 @@ mod parsing {
 			}
 			let starting_position = s[starting_position_start..].parse()
 				.map_err(|e| InvalidStartingPosition { column: starting_position_start + 1, source: IntError::Parsing(e) })?;
-			if !(1..=10).contains(&starting_position) {
+			if starting_position < 1 {
 				return Err(InvalidStartingPosition { column: starting_position_start + 1, source: IntError::Invalid(starting_position) })
 			}
 
@@ -211,18 +509,15 @@ mod parsing {
 		InvalidPlayer { line: usize, source: PlayerError},
 	}
 
-	pub(super) fn players_from_str(s: &str) -> Result<[Player; 2], PlayersError> {
+	pub(super) fn players_from_str(s: &str) -> Result<Vec<Player>, PlayersError> {
 		use PlayersError::*;
-		let mut lines = s.lines();
-		let players = lines.by_ref()
+		let players = s.lines()
 			.enumerate()
 			.map(|(l, line)| line.parse()
 				.map_err(|e| InvalidPlayer { line: l + 1, source: e }))
-			.take(2)
 			.collect::<Result<Vec<_>, _>>()?;
-		if players.len() < 2 { Err(InvalidFormat { line: players.len() + 1 }) }
-		else if lines.next().is_some() { Err(InvalidFormat { line: 3 }) }
-		else { Ok(players.try_into().unwrap()) }
+		if players.is_empty() { Err(InvalidFormat { line: 1 }) }
+		else { Ok(players) }
 	}
 }
 
@@ -233,9 +528,43 @@ fn tests() -> Result<(), parsing::PlayersError>{
 		Player 1 starting position: 4
 		Player 2 starting position: 8
 	" };
-	assert_eq!(part1_impl(parsing::players_from_str(INPUT)?), 739785);
+	let players = parsing::players_from_str(INPUT)?;
+	assert_eq!(part1_impl(&players, &GameRules::DETERMINISTIC), Ok(739785));
 	assert_eq!(part1(), 920580);
-	assert_eq!(part2_impl(parsing::players_from_str(INPUT)?), 444356092776315);
+	assert_eq!(part1_impl(&players, &GameRules { die_sides: 6, target_score: 100, ..GameRules::DETERMINISTIC }), Ok(7440));
+	assert_eq!(quantum_win_universes(&players, &GameRules::QUANTUM), Ok(vec![444356092776315, 341960390180808]));
+	assert_eq!(part2_impl(&players, &GameRules::QUANTUM), Ok(444356092776315));
 	assert_eq!(part2(), 647920021341197);
+
+	const INPUT_THREE_PLAYERS: &str = indoc::indoc! { "
+		Player 1 starting position: 4
+		Player 2 starting position: 8
+		Player 3 starting position: 1
+	" };
+	for (target_score, win_universes) in [
+		(30, Ok(vec![455119916668356680878, 375230170025322703943])),
+		(50, Ok(vec![11982739236155752973151191694265066, 10664649344489804307008470880126235])),
+		(60, Err(GameError::UniversesOverflow)),
+	] {
+		assert_eq!(quantum_win_universes(&players, &GameRules { target_score, ..GameRules::QUANTUM }), win_universes);
+	}
+
+	let players = parsing::players_from_str(INPUT_THREE_PLAYERS)?;
+	let rules = GameRules::DETERMINISTIC;
+	assert_eq!(deterministic_outcome(&players, &rules), Ok(DeterministicOutcome { winner: 0, scores: vec![1001, 987, 855], rolls: 1380 }));
+	assert_eq!(part1_impl(&players, &rules), Ok(855 * 1380));
+	let rules = GameRules { target_score: 10, ..GameRules::QUANTUM };
+	assert_eq!(quantum_win_universes(&players, &rules), Ok(vec![1932136999, 2531115256, 4095622876]));
+
+	const INPUT_BEYOND_BOARD: &str = indoc::indoc! { "
+		Player 1 starting position: 11
+		Player 2 starting position: 8
+	" };
+	let players = parsing::players_from_str(INPUT_BEYOND_BOARD)?;
+	assert_eq!(part1_impl(&players, &GameRules::DETERMINISTIC), Err(GameError::InvalidStartingPosition { id: 1, position: 11 }));
+	assert_eq!(part2_impl(&players, &GameRules::QUANTUM), Err(GameError::InvalidStartingPosition { id: 1, position: 11 }));
+	assert_eq!(part1_impl(&players, &GameRules { board_size: 12, ..GameRules::DETERMINISTIC }), Ok(651846));
+	assert_eq!(part1_impl(&[], &GameRules::DETERMINISTIC), Err(GameError::NoPlayers));
+	assert_eq!(part2_impl(&[], &GameRules::QUANTUM), Err(GameError::NoPlayers));
 	Ok(())
 }
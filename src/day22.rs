@@ -4,6 +4,7 @@ use std::{ops::RangeInclusive, collections::HashSet};
 use itertools::iproduct;
 
 
+#[derive(Clone)]
 #[cfg_attr(test, derive(Debug))]
 struct Step {
 	on: bool,
@@ -11,12 +12,15 @@ struct Step {
 }
 
 impl Step {
-	fn cuboid_fully_inside_50x_unit_region(&self) -> bool {
-		self.cuboid_ranges.iter()
-			.all(|r| r.start().abs().max(r.end().abs()) <= 50)
+	/// Clips the step’s cuboid to `region`, or returns `None` if it lies fully outside.
+	fn clipped(&self, region: &[RangeInclusive<i32>; 3]) -> Option<Step> {
+		self.cuboid_ranges.intersection(region)
+			.map(|cuboid_ranges| Step { on: self.on, cuboid_ranges })
 	}
 }
 
+const INITIALIZATION_REGION: [RangeInclusive<i32>; 3] = [-50..=50, -50..=50, -50..=50];
+
 
 trait Intersection: Sized {
 	fn intersection(&self, other: &Self) -> Option<Self>;
@@ -42,22 +46,271 @@ impl Intersection for [RangeInclusive<i32>; 3] {
 }
 
 
+#[derive(Debug, PartialEq, Eq)]
+enum VolumeError {
+	/// The volume (or an intermediate sum) is not representable.
+	Overflow,
+	/// The signed parts sum to a negative volume (i.e. the set is malformed).
+	Negative,
+}
+
 trait Volume {
-	fn volume(&self) -> usize;
+	fn volume(&self) -> Result<u128, VolumeError>;
 }
 
 impl Volume for RangeInclusive<i32> {
-	fn volume(&self) -> usize {
-		(*self.end() - *self.start()) as usize + 1
+	fn volume(&self) -> Result<u128, VolumeError> {
+		let len = i64::from(*self.end()) - i64::from(*self.start()) + 1;
+		u128::try_from(len).map_err(|_| VolumeError::Negative)
 	}
 }
 
 impl Volume for [RangeInclusive<i32>; 3] {
-	fn volume(&self) -> usize {
-		self.iter().map(|r| r.volume()).product()
+	fn volume(&self) -> Result<u128, VolumeError> {
+		self.iter().try_fold(1u128, |volume, r|
+			volume.checked_mul(r.volume()?).ok_or(VolumeError::Overflow))
+	}
+}
+
+
+trait Reactor: Default + Volume {
+	fn apply(&mut self, step: Step);
+	/// Counts the lit cubes within `region`.
+	#[allow(dead_code)]
+	fn lit_within(&self, region: &[RangeInclusive<i32>; 3]) -> Result<u128, VolumeError>;
+	#[allow(dead_code)]
+	fn is_lit(&self, cube: &[i32; 3]) -> bool;
+}
+
+
+/// A set of cubes, represented as a list of signed (possibly overlapping) cuboid
+/// parts, such that a cube is in the set iff the signed count of the parts
+/// containing it is one (i.e. inclusion–exclusion).
+#[derive(Clone, Default)]
+#[cfg_attr(test, derive(Debug))]
+struct CuboidSet {
+	parts: Vec<(bool, [RangeInclusive<i32>; 3])>,
+}
+
+impl CuboidSet {
+	fn insert(&mut self, cuboid: [RangeInclusive<i32>; 3]) {
+		self.remove(&cuboid);
+		self.parts.push((true, cuboid));
+	}
+
+	fn remove(&mut self, cuboid: &[RangeInclusive<i32>; 3]) {
+		for j in 0..self.parts.len() {
+			let &(part_on, ref part_ranges) = &self.parts[j];
+			if let Some(intersection) = cuboid.intersection(part_ranges) {
+				self.parts.push((!part_on, intersection));
+			}
+		}
+	}
+
+	#[allow(dead_code)]
+	fn union(&self, other: &Self) -> Self {
+		let mut union = self.difference(other);
+		union.parts.extend(other.parts.iter().cloned());
+		union
+	}
+
+	#[allow(dead_code)]
+	fn difference(&self, other: &Self) -> Self {
+		let mut difference = self.clone();
+		difference.parts.extend(self.intersection(other).into_iter()
+			.flat_map(|i| i.parts)
+			.map(|(on, ranges)| (!on, ranges)));
+		difference
+	}
+
+	#[allow(dead_code)]
+	fn contains(&self, cube: &[i32; 3]) -> bool {
+		self.parts.iter()
+			.filter(|(_, ranges)| ranges.iter().zip(cube).all(|(r, c)| r.contains(c)))
+			.map(|&(on, _)| if on { 1 } else { -1 })
+			.sum::<i32>() > 0
+	}
+
+	/// The smallest cuboid containing all cuboids inserted into the set
+	/// (not necessarily tight when parts of those were later removed).
+	#[allow(dead_code)]
+	fn bounding_box(&self) -> Option<[RangeInclusive<i32>; 3]> {
+		if matches!(self.volume(), Ok(0)) { return None }
+		self.parts.iter()
+			.filter(|(on, _)| *on)
+			.map(|(_, ranges)| ranges.clone())
+			.reduce(|bb, ranges| [0, 1, 2].map(|i|
+				*bb[i].start().min(ranges[i].start())..=*bb[i].end().max(ranges[i].end())))
+	}
+}
+
+impl Reactor for CuboidSet {
+	fn apply(&mut self, step: Step) {
+		if step.on { self.insert(step.cuboid_ranges) }
+		else { self.remove(&step.cuboid_ranges) }
+	}
+
+	fn lit_within(&self, region: &[RangeInclusive<i32>; 3]) -> Result<u128, VolumeError> {
+		self.intersection(&region.clone().into()).map_or(Ok(0), |i| i.volume())
+	}
+
+	fn is_lit(&self, cube: &[i32; 3]) -> bool {
+		self.contains(cube)
+	}
+}
+
+impl From<[RangeInclusive<i32>; 3]> for CuboidSet {
+	fn from(cuboid: [RangeInclusive<i32>; 3]) -> Self {
+		CuboidSet { parts: vec![(true, cuboid)] }
+	}
+}
+
+impl Intersection for CuboidSet {
+	fn intersection(&self, other: &Self) -> Option<Self> {
+		let parts = iproduct!(&self.parts, &other.parts)
+			.filter_map(|((self_on, self_ranges), (other_on, other_ranges))|
+				self_ranges.intersection(other_ranges).map(|i| (self_on == other_on, i)))
+			.collect::<Vec<_>>();
+		let intersection = CuboidSet { parts };
+		if matches!(intersection.volume(), Ok(0)) { None } else { Some(intersection) }
+	}
+}
+
+impl CuboidSet {
+	fn signed_volume(parts: &[(bool, [RangeInclusive<i32>; 3])]) -> Result<i128, VolumeError> {
+		parts.iter().try_fold(0i128, |volume, (on, ranges)| {
+			let part_volume = i128::try_from(ranges.volume()?).map_err(|_| VolumeError::Overflow)?;
+			if *on { volume.checked_add(part_volume) } else { volume.checked_sub(part_volume) }
+				.ok_or(VolumeError::Overflow)
+		})
+	}
+}
+
+impl Volume for CuboidSet {
+	fn volume(&self) -> Result<u128, VolumeError> {
+		u128::try_from(CuboidSet::signed_volume(&self.parts)?).map_err(|_| VolumeError::Negative)
+	}
+}
+
+
+/// A `CuboidSet` that remembers which of its parts each applied step added,
+/// so that it can be rolled back to any earlier step without replaying.
+#[allow(dead_code)]
+#[derive(Default)]
+struct RebootHistory {
+	cuboid_set: CuboidSet,
+	/// Per applied step, the number of parts and the lit volume after it.
+	steps: Vec<(usize, u128)>,
+}
+
+#[allow(dead_code)]
+impl RebootHistory {
+	/// Applies `step`, returning the lit volume after it.
+	fn apply(&mut self, step: Step) -> Result<u128, VolumeError> {
+		let parts_len = self.cuboid_set.parts.len();
+		self.cuboid_set.apply(step);
+		let volume = CuboidSet::signed_volume(&self.cuboid_set.parts[parts_len..])
+			.and_then(|delta| self.lit_volume().checked_add_signed(delta).ok_or(VolumeError::Overflow));
+		match volume {
+			Ok(volume) => self.steps.push((self.cuboid_set.parts.len(), volume)),
+			Err(_) => self.cuboid_set.parts.truncate(parts_len),
+		}
+		volume
+	}
+
+	fn len(&self) -> usize {
+		self.steps.len()
+	}
+
+	fn lit_volume(&self) -> u128 {
+		self.steps.last().map_or(0, |&(_, volume)| volume)
+	}
+
+	/// Rolls back to the state after the first `len` steps.
+	fn rollback(&mut self, len: usize) {
+		self.steps.truncate(len);
+		self.cuboid_set.parts.truncate(self.steps.last().map_or(0, |&(parts_len, _)| parts_len));
+	}
+}
+
+/// Yields the lit volume after each step.
+#[allow(dead_code)]
+fn lit_volumes(input_steps: impl IntoIterator<Item = Step>) -> impl Iterator<Item = Result<u128, VolumeError>> {
+	let mut history = RebootHistory::default();
+	input_steps.into_iter().map(move |step| history.apply(step))
+}
+
+/// A set of cubes, represented as a list of disjoint cuboids; cuboids that
+/// overlap a newly inserted or removed cuboid are split around it. Splitting
+/// leaves more cuboids than `CuboidSet` has parts, so this isn’t any faster
+/// (see `engines_benchmark`), but it’s a useful cross-check.
+#[derive(Default)]
+struct DisjointCuboids {
+	cuboids: Vec<[RangeInclusive<i32>; 3]>,
+}
+
+impl DisjointCuboids {
+	fn remove(&mut self, cuboid: &[RangeInclusive<i32>; 3]) {
+		let mut j = 0;
+		while j < self.cuboids.len() {
+			let Some(intersection) = self.cuboids[j].intersection(cuboid) else { j += 1; continue };
+			let mut rest = self.cuboids.swap_remove(j);
+			for axis in 0..3 {
+				let (rest_start, rest_end) = (*rest[axis].start(), *rest[axis].end());
+				let (intersection_start, intersection_end) = (*intersection[axis].start(), *intersection[axis].end());
+				if rest_start < intersection_start {
+					let mut below = rest.clone();
+					below[axis] = rest_start..=intersection_start - 1;
+					self.cuboids.push(below);
+				}
+				if rest_end > intersection_end {
+					let mut above = rest.clone();
+					above[axis] = intersection_end + 1..=rest_end;
+					self.cuboids.push(above);
+				}
+				rest[axis] = intersection[axis].clone();
+			}
+			// Pieces pushed above are disjoint from `cuboid`, and the last
+			// swapped-in cuboid (if any) is now at `j`, so don’t advance.
+		}
 	}
 }
 
+impl Reactor for DisjointCuboids {
+	fn apply(&mut self, step: Step) {
+		self.remove(&step.cuboid_ranges);
+		if step.on { self.cuboids.push(step.cuboid_ranges) }
+	}
+
+	fn lit_within(&self, region: &[RangeInclusive<i32>; 3]) -> Result<u128, VolumeError> {
+		self.cuboids.iter()
+			.filter_map(|c| c.intersection(region))
+			.try_fold(0u128, |volume, i| volume.checked_add(i.volume()?).ok_or(VolumeError::Overflow))
+	}
+
+	fn is_lit(&self, cube: &[i32; 3]) -> bool {
+		self.cuboids.iter()
+			.any(|c| c.iter().zip(cube).all(|(r, c)| r.contains(c)))
+	}
+}
+
+impl Volume for DisjointCuboids {
+	fn volume(&self) -> Result<u128, VolumeError> {
+		self.cuboids.iter()
+			.try_fold(0u128, |volume, c| volume.checked_add(c.volume()?).ok_or(VolumeError::Overflow))
+	}
+}
+
+
+#[allow(dead_code)]
+#[derive(Clone, Copy)]
+#[cfg_attr(test, derive(Debug))]
+enum Engine {
+	/// Signed inclusion–exclusion parts (see `CuboidSet`).
+	SignedParts,
+	/// Disjoint cuboids (see `DisjointCuboids`).
+	DisjointCuboids,
+}
 
 fn input_steps_from_str(s: &str) -> Vec<Step> {
 	parsing::steps_from_str(s).unwrap()
@@ -72,7 +325,7 @@ fn input_steps() -> Vec<Step> {
 fn part1_brute(input_steps: Vec<Step>) -> usize {
 	let mut on = HashSet::new();
 	for step in input_steps {
-		if !step.cuboid_fully_inside_50x_unit_region() { continue }
+		let Some(step) = step.clipped(&INITIALIZATION_REGION) else { continue };
 		for cube in iproduct!(
 			step.cuboid_ranges[0].clone(),
 			step.cuboid_ranges[1].clone(),
@@ -85,36 +338,32 @@ fn part1_brute(input_steps: Vec<Step>) -> usize {
 	on.len()
 }
 
-fn part1and2_impl(input_steps: Vec<Step>, discard_cuboid_partly_outside_50x_unit_region: bool) -> usize {
-	let mut cuboid_parts = {
-		let steps_len = input_steps.len();
-		Vec::with_capacity(steps_len * (steps_len + 1) / 2)
-	};
-
+/// Applies all steps, first clipping them to `region` (if any).
+fn rebooted<R: Reactor>(input_steps: Vec<Step>, region: Option<&[RangeInclusive<i32>; 3]>) -> R {
+	let mut reactor = R::default();
 	for step in input_steps {
-		if discard_cuboid_partly_outside_50x_unit_region && !step.cuboid_fully_inside_50x_unit_region() { continue }
-
-		for j in 0..cuboid_parts.len() {
-			let &(prev_cuboid_part_on, ref prev_cuboid_part_ranges) = &cuboid_parts[j];
-			if let Some(intersection) = step.cuboid_ranges.intersection(prev_cuboid_part_ranges) {
-				cuboid_parts.push((!(prev_cuboid_part_on as bool), intersection));
-			}
-		}
-		if step.on { cuboid_parts.push((true, step.cuboid_ranges)); }
+		let step = match region {
+			Some(region) => match step.clipped(region) { Some(step) => step, None => continue },
+			None => step,
+		};
+		reactor.apply(step);
 	}
+	reactor
+}
 
-	cuboid_parts.into_iter()
-		.map(|(on, ranges)|
-			if on { 1 } else { -1 } * ranges.volume() as i64)
-		.sum::<i64>() as usize
+fn part1and2_impl(input_steps: Vec<Step>, region: Option<&[RangeInclusive<i32>; 3]>, engine: Engine) -> Result<u128, VolumeError> {
+	match engine {
+		Engine::SignedParts => rebooted::<CuboidSet>(input_steps, region).volume(),
+		Engine::DisjointCuboids => rebooted::<DisjointCuboids>(input_steps, region).volume(),
+	}
 }
 
-pub(crate) fn part1() -> usize {
-	part1and2_impl(input_steps(), true)
+pub(crate) fn part1() -> u128 {
+	part1and2_impl(input_steps(), Some(&INITIALIZATION_REGION), Engine::SignedParts).unwrap()
 }
 
-pub(crate) fn part2() -> usize {
-	part1and2_impl(input_steps(), false)
+pub(crate) fn part2() -> u128 {
+	part1and2_impl(input_steps(), None, Engine::SignedParts).unwrap()
 }
 
 
@@ -249,6 +498,105 @@ mod parsing {
 }
 
 
+#[test]
+fn cuboid_set() {
+	let a = CuboidSet::from([0..=2, 0..=2, 0..=2]);
+	let b = CuboidSet::from([1..=3, 1..=3, 1..=3]);
+	assert_eq!(a.volume(), Ok(27));
+	assert_eq!(a.union(&b).volume(), Ok(46));
+	assert_eq!(a.intersection(&b).map(|i| i.volume()), Some(Ok(8)));
+	assert_eq!(a.difference(&b).volume(), Ok(19));
+	assert_eq!(b.difference(&a).union(&a.difference(&b)).volume(), Ok(38));
+	assert!(a.intersection(&CuboidSet::from([3..=4, 0..=2, 0..=2])).is_none());
+
+	let d = a.difference(&b);
+	assert!(d.contains(&[0, 0, 0]));
+	assert!(!d.contains(&[1, 1, 1]));
+	assert!(!d.contains(&[3, 3, 3]));
+	assert!(d.union(&b).contains(&[3, 3, 3]));
+	assert_eq!(d.bounding_box(), Some([0..=2, 0..=2, 0..=2]));
+	assert_eq!(a.difference(&a).bounding_box(), None);
+
+	let mut s = CuboidSet::default();
+	s.insert([10..=12, 10..=12, 10..=12]);
+	s.insert([11..=13, 11..=13, 11..=13]);
+	s.remove(&[9..=11, 9..=11, 9..=11]);
+	s.insert([10..=10, 10..=10, 10..=10]);
+	assert_eq!(s.volume(), Ok(39));
+	assert!(s.contains(&[10, 10, 10]) && !s.contains(&[11, 11, 11]));
+}
+
+
+#[test]
+fn reactor_queries() {
+	const INPUT: &str = indoc::indoc! { "
+		on x=10..12,y=10..12,z=10..12
+		on x=11..13,y=11..13,z=11..13
+		off x=9..11,y=9..11,z=9..11
+		on x=10..10,y=10..10,z=10..10
+	" };
+	fn check(reactor: impl Reactor) {
+		assert_eq!(reactor.volume(), Ok(39));
+		assert_eq!(reactor.lit_within(&[10..=10, 10..=10, 10..=10]), Ok(1));
+		assert_eq!(reactor.lit_within(&[9..=11, 9..=11, 9..=11]), Ok(1));
+		assert_eq!(reactor.lit_within(&[12..=20, 0..=20, 0..=20]), Ok(3 * 3 + 2 * 3 + 2 * 2 * 2));
+		assert_eq!(reactor.lit_within(&[14..=20, 0..=20, 0..=20]), Ok(0));
+		assert!(reactor.is_lit(&[10, 10, 10]));
+		assert!(!reactor.is_lit(&[11, 11, 11]));
+		assert!(reactor.is_lit(&[13, 13, 13]));
+		assert!(!reactor.is_lit(&[14, 13, 13]));
+	}
+	check(rebooted::<CuboidSet>(input_steps_from_str(INPUT), None));
+	check(rebooted::<DisjointCuboids>(input_steps_from_str(INPUT), None));
+	assert_eq!(part1and2_impl(input_steps_from_str(INPUT), Some(&[11..=20, 11..=20, 11..=20]), Engine::SignedParts), Ok(3 * 3 * 3 - 1));
+}
+
+
+#[test]
+fn reboot_history() {
+	const INPUT: &str = indoc::indoc! { "
+		on x=10..12,y=10..12,z=10..12
+		on x=11..13,y=11..13,z=11..13
+		off x=9..11,y=9..11,z=9..11
+		on x=10..10,y=10..10,z=10..10
+	" };
+	assert_eq!(lit_volumes(input_steps_from_str(INPUT)).collect::<Vec<_>>(), [Ok(27), Ok(46), Ok(38), Ok(39)]);
+
+	let mut history = RebootHistory::default();
+	for step in input_steps_from_str(INPUT) { history.apply(step).unwrap(); }
+	assert_eq!((history.len(), history.lit_volume()), (4, 39));
+	history.rollback(2);
+	assert_eq!((history.len(), history.lit_volume()), (2, 46));
+	assert_eq!(history.cuboid_set.volume(), Ok(46));
+	assert!(history.cuboid_set.contains(&[11, 11, 11]));
+	history.rollback(3);
+	assert_eq!(history.len(), 2);
+	assert_eq!(history.apply(input_steps_from_str(INPUT).swap_remove(3)), Ok(46));
+	assert_eq!(history.apply(input_steps_from_str(INPUT).swap_remove(2)), Ok(38));
+	history.rollback(0);
+	assert_eq!((history.len(), history.lit_volume(), history.cuboid_set.parts.len()), (0, 0, 0));
+}
+
+
+#[test]
+fn volumes() {
+	const FULL: RangeInclusive<i32> = i32::MIN..=i32::MAX;
+	assert_eq!(FULL.volume(), Ok(1 << 32));
+	assert_eq!([FULL, FULL, FULL].volume(), Ok(1 << 96));
+
+	let steps = input_steps_from_str(&format!(indoc::indoc! { "
+		on x={0}..{1},y={0}..{1},z={0}..{1}
+		on x=0..{1},y={0}..{1},z={0}..{1}
+		off x={0}..-1,y={0}..{1},z={0}..{1}
+	" }, i32::MIN, i32::MAX));
+	assert_eq!(lit_volumes(steps.clone()).collect::<Vec<_>>(), [Ok(1 << 96), Ok(1 << 96), Ok(1 << 95)]);
+	assert_eq!(part1and2_impl(steps.clone(), None, Engine::DisjointCuboids), Ok(1 << 95));
+	assert_eq!(part1and2_impl(steps, Some(&INITIALIZATION_REGION), Engine::SignedParts), Ok(51 * 101 * 101));
+
+	assert_eq!(CuboidSet { parts: vec![(false, [0..=1, 0..=1, 0..=1])] }.volume(), Err(VolumeError::Negative));
+}
+
+
 #[test]
 fn tests() {
 	const INPUT_PART1: &str = indoc::indoc! { "
@@ -276,7 +624,13 @@ fn tests() {
 		on x=967..23432,y=45373..81175,z=27513..53682
 	" };
 	assert_eq!(part1_brute(input_steps_from_str(INPUT_PART1)), 590784);
-	assert_eq!(part1and2_impl(input_steps_from_str(INPUT_PART1), true), 590784);
+	for engine in [Engine::SignedParts, Engine::DisjointCuboids] {
+		assert_eq!(part1and2_impl(input_steps_from_str(INPUT_PART1), Some(&INITIALIZATION_REGION), engine), Ok(590784));
+	}
+	assert_eq!(rebooted::<CuboidSet>(input_steps_from_str(INPUT_PART1), None)
+		.lit_within(&INITIALIZATION_REGION), Ok(590784));
+	assert_eq!(rebooted::<DisjointCuboids>(input_steps_from_str(INPUT_PART1), None)
+		.lit_within(&INITIALIZATION_REGION), Ok(590784));
 	assert_eq!(part1(), 580098);
 
 	const INPUT_PART2: &str = indoc::indoc! { "
@@ -341,6 +695,41 @@ fn tests() {
 		on x=-53470..21291,y=-120233..-33476,z=-44150..38147
 		off x=-93533..-4276,y=-16170..68771,z=-104985..-24507
 	" };
-	assert_eq!(part1and2_impl(input_steps_from_str(INPUT_PART2), false), 2758514936282235);
+	for engine in [Engine::SignedParts, Engine::DisjointCuboids] {
+		assert_eq!(part1and2_impl(input_steps_from_str(INPUT_PART2), None, engine), Ok(2758514936282235));
+	}
+	assert_eq!(rebooted::<CuboidSet>(input_steps_from_str(INPUT_PART2), None)
+		.lit_within(&INITIALIZATION_REGION), Ok(474140));
+	assert_eq!(rebooted::<DisjointCuboids>(input_steps_from_str(INPUT_PART2), None)
+		.lit_within(&INITIALIZATION_REGION), Ok(474140));
+	assert_eq!(part1and2_impl(input_steps(), None, Engine::DisjointCuboids), Ok(1134725012490723));
 	assert_eq!(part2(), 1134725012490723);
 }
+
+
+#[test]
+#[ignore = "benchmark"]
+fn engines_benchmark() {
+	fn random_steps(rng: &mut impl rand::Rng, len: usize) -> Vec<Step> {
+		(0..len).map(|_| Step {
+			on: rng.gen_bool(0.75),
+			cuboid_ranges: [(); 3].map(|_| {
+				let from = rng.gen_range(-100_000..100_000);
+				from..=from + rng.gen_range(0..40_000)
+			}),
+		}).collect()
+	}
+
+	use rand::SeedableRng as _;
+	let mut rng = rand::rngs::StdRng::seed_from_u64(22);
+	for len in [250, 500, 1000] {
+		let steps = random_steps(&mut rng, len);
+		let results = [Engine::SignedParts, Engine::DisjointCuboids].map(|engine| {
+			let start = std::time::Instant::now();
+			let volume = part1and2_impl(steps.clone(), None, engine);
+			println!("{len} steps, {engine:?}: {:?}", start.elapsed());
+			volume
+		});
+		assert_eq!(results[0], results[1]);
+	}
+}
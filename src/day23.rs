@@ -1,205 +1,196 @@
 // Copyright (c) 2022 Bastiaan Marinus van de Weerd
 
-use std::ops::Range;
+use std::rc::Rc;
 
-const BURROW_FOLDED: &str = indoc::indoc! { "
-	#############
-	#...........#
-	###a#b#c#d###
-	  #a#b#c#d#
-	  #########
-" };
-const BURROW_FOLDED_NUM_SPACES: usize = 19;
-const BURROW_UNFOLDED_NUM_SPACES: usize = 27;
-const BURROW_FOLDED_NUM_AMPHIPODS: usize = 8;
-const BURROW_UNFOLDED_NUM_AMPHIPODS: usize = 16;
-const BURROW_ALL_LOCS: [[usize; 2]; BURROW_UNFOLDED_NUM_SPACES] = [
-	[1, 1], [2, 1], [3, 1], [4, 1], [5, 1], [6, 1], [7, 1], [8, 1], [9, 1], [10, 1], [11, 1],
-	                [3, 2],         [5, 2],         [7, 2],         [9, 2],
-	                [3, 3],         [5, 3],         [7, 3],         [9, 3],
-	                [3, 4],         [5, 4],         [7, 4],         [9, 4],
-	                [3, 5],         [5, 5],         [7, 5],         [9, 5]];
-const BURROW_SPACES_IN_FOLD: Range<usize> = 15..23;
-const BURROW_AMPHIPOD_KINDS_IN_FOLD: [AmphipodKind; 8] = [
-	AmphipodKind::Desert, AmphipodKind::Copper, AmphipodKind::Bronze, AmphipodKind::Amber,
-	AmphipodKind::Desert, AmphipodKind::Bronze, AmphipodKind::Amber, AmphipodKind::Copper];
-const BURROW_FOLDED_STEPS: [usize; 36] = [
-	 1,  2,  0,  3, 11,  1,  4,  2, 5, 12, 3, 6, 4, 7, 13, 5, 8, 6, 9, 14, 7, 10, 8, 9,
-	 2, 15,  4, 16,  6,  17, 8, 18,
-	11, 12, 13, 14];
-const BURROW_UNFOLDED_STEPS: [usize; 52] = [
-	 1,  2,  0,  3, 11,  1,  4,  2, 5, 12, 3, 6, 4, 7, 13, 5, 8, 6, 9, 14, 7, 10, 8, 9,
-	 2, 15,  4, 16,  6,  17, 8, 18,
-	11, 19, 12, 20, 13, 21, 14, 22, 
-	15, 23, 16, 24, 17, 25, 18, 26,
-	19, 20, 21, 22];
-const BURROW_FOLDED_SPACE_STEPS: [usize; BURROW_FOLDED_NUM_SPACES + 1] = [
-	 0,  1,  3,  6,  8, 11, 13, 16, 18, 21, 23,
-	24, 26, 28, 30,
-	32, 33, 34, 35,
-	36];
-const BURROW_UNFOLDED_SPACE_STEPS: [usize; BURROW_UNFOLDED_NUM_SPACES + 1] = [
-	 0,  1,  3,  6,  8, 11, 13, 16, 18, 21, 23,
-	24, 26, 28, 30,
-	32, 34, 36, 38,
-	40, 42, 44, 46,
-	48, 49, 50, 51,
-	52];
-const BURROW_MOVE_STEP_COUNTS: [[usize; BURROW_ALL_LOCS.len()]; BURROW_ALL_LOCS.len()] = [
-	//                                           1A   B   C   D  2A   B   C   D  3A   B   C   D  4A   B   C   D
-	[ 0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10,  3,  5,  7,  9,  4,  6,  8, 10,  5,  7,  9, 11,  6,  8, 10, 12],
-	[ 1,  0,  1,  2,  3,  4,  5,  6,  7,  8,  9,  2,  4,  6,  8,  3,  5,  7,  9,  4,  6,  8, 10,  5,  7,  9, 11],
-	[ 2,  1,  0,  1,  2,  3,  4,  5,  6,  7,  8,  1,  3,  5,  7,  2,  4,  6,  8,  3,  5,  7,  9,  4,  6,  8, 10],
-	[ 3,  2,  1,  0,  1,  2,  3,  4,  5,  6,  7,  2,  2,  4,  6,  3,  3,  5,  7,  4,  4,  6,  8,  5,  5,  7,  9],
-	[ 4,  3,  2,  1,  0,  1,  2,  3,  4,  5,  6,  3,  1,  3,  5,  4,  2,  4,  6,  5,  3,  5,  7,  6,  4,  6,  8],
-	[ 5,  4,  3,  2,  1,  0,  1,  2,  3,  4,  5,  4,  2,  2,  4,  5,  3,  3,  5,  6,  4,  4,  6,  7,  5,  5,  7],
-	[ 6,  5,  4,  3,  2,  1,  0,  1,  2,  3,  4,  5,  3,  1,  3,  6,  4,  2,  4,  7,  5,  3,  5,  8,  6,  4,  6],
-	[ 7,  6,  5,  4,  3,  2,  1,  0,  1,  2,  3,  6,  4,  2,  2,  7,  5,  3,  3,  8,  6,  4,  4,  9,  7,  5,  5],
-	[ 8,  7,  6,  5,  4,  3,  2,  1,  0,  1,  2,  7,  5,  3,  1,  8,  6,  4,  2,  9,  7,  5,  3, 10,  8,  6,  4],
-	[ 9,  8,  7,  6,  5,  4,  3,  2,  1,  0,  1,  8,  6,  4,  2,  9,  7,  5,  3, 10,  8,  6,  4, 11,  9,  7,  5],
-	[10,  9,  8,  7,  6,  5,  4,  3,  2,  1,  0,  9,  7,  5,  3, 10,  8,  6,  4, 11,  8,  7,  5, 12, 10,  8,  6],
-/**/[ 3,  2,  1,  2,  3,  4,  5,  6,  7,  8,  9,  0,  4,  6,  8,  1,  5,  7,  9,  2,  6,  8, 10,  3,  7,  9, 11],
-	[ 5,  4,  3,  2,  1,  2,  3,  4,  5,  6,  7,  4,  0,  4,  6,  5,  1,  5,  7,  6,  2,  6,  8,  7,  3,  7,  9],
-	[ 7,  6,  5,  4,  3,  2,  1,  2,  3,  4,  5,  6,  4,  0,  4,  7,  5,  1,  5,  9,  6,  2,  6,  9,  7,  3,  7],
-	[ 9,  8,  7,  6,  5,  4,  3,  2,  1,  2,  3,  8,  6,  4,  0,  9,  7,  5,  1, 10, 10,  6,  2, 11,  9,  7,  3],
-/**/[ 4,  3,  2,  3,  4,  5,  6,  7,  8,  9, 10,  1,  5,  7,  9,  0,  6,  8, 10,  1,  7,  9, 11,  2,  8, 10, 12],
-	[ 6,  5,  4,  3,  2,  3,  4,  5,  6,  7,  8,  5,  1,  5,  7,  6,  0,  6,  8,  7,  1,  7,  9,  8,  2,  8, 10],
-	[ 8,  7,  6,  5,  4,  3,  2,  3,  4,  5,  6,  7,  5,  1,  5,  8,  6,  0,  6,  9,  7,  1,  7, 10,  8,  2,  8], 
-	[10,  9,  8,  7,  6,  5,  4,  3,  2,  3,  4,  9,  7,  5,  1, 10,  8,  6,  0, 11,  9,  7,  1, 12, 10,  8,  2],
-/**/[ 5,  4,  3,  4,  5,  6,  7,  8,  9, 10, 11,  2,  6,  8, 10,  1,  7,  9, 11,  0,  8, 10, 12,  1,  9, 11, 13],
-	[ 7,  6,  5,  4,  3,  4,  5,  6,  7,  8,  9,  6,  2,  6,  8,  7,  1,  7,  9,  8,  0,  8, 10,  9,  1,  9, 11],
-	[ 9,  8,  7,  6,  5,  4,  3,  4,  5,  6,  7,  8,  6,  2,  6,  9,  7,  1,  7, 10,  8,  0,  8, 11,  9,  1,  9],
-	[11, 10,  9,  8,  7,  6,  5,  4,  3,  4,  5, 10,  8,  8,  2, 11,  9,  7,  1, 12, 10,  8,  0, 13, 11,  9,  1],
-/**/[ 6,  5,  4,  5,  6,  7,  8,  9, 10, 11, 12,  3,  7,  9, 11,  2,  8, 10, 12,  1,  9, 11, 13,  0, 10, 12, 14],
-	[ 8,  7,  6,  5,  4,  5,  6,  7,  8,  9, 10,  7,  3,  7,  9,  8,  2,  8, 10,  9,  1,  9, 11, 10,  0, 10, 12],
-	[10,  9,  8,  7,  6,  5,  4,  5,  6,  7,  8,  9,  7,  3,  7, 10,  8,  2,  8, 11,  9,  1,  9, 12, 10,  0, 10],
-	[12, 11, 10,  9,  8,  7,  6,  5,  4,  5,  6, 11,  9,  7,  3, 12, 10,  8,  2, 13, 11,  9,  1, 14, 12, 10,  0]];
-const BURROW_HALLWAY_SPACES: Range<usize> = 0..11;
-const BURROW_OUTSIDE_SIDE_ROOM_SPACES: [usize; 4] = [2, 4, 6, 8];
-const BURROW_TARGET_ROOMS: [(AmphipodKind, [usize; BURROW_UNFOLDED_NUM_AMPHIPODS / 4]); 4] = [
-	(AmphipodKind::Amber, [11, 15, 19, 23]),
-	(AmphipodKind::Bronze, [12, 16, 20, 24]),
-	(AmphipodKind::Copper, [13, 17, 21, 25]),
-	(AmphipodKind::Desert, [14, 18, 22, 26]),
-];
-const BURROW_FOLDED_TARGET_ROOM_SPACE_PERMUTATIONS: [[usize; BURROW_FOLDED_NUM_AMPHIPODS / 4]; 2] = [
-	[0, 1], [1, 0]];
-const BURROW_UNFOLDED_TARGET_ROOM_SPACE_PERMUTATIONS: [[usize; BURROW_UNFOLDED_NUM_AMPHIPODS / 4]; 24] = [
-	[0, 1, 2, 3], [0, 2, 3, 1], [0, 3, 1, 2], [0, 1, 3, 2], [0, 3, 2, 1], [0, 2, 1, 3],
-	[1, 2, 3, 0], [1, 3, 0, 2], [1, 0, 2, 3], [1, 2, 0, 3], [1, 0, 3, 2], [1, 3, 2, 0],
-	[2, 3, 0, 1], [2, 0, 1, 3], [2, 1, 3, 0], [2, 3, 1, 0], [2, 1, 0, 3], [2, 0, 3, 1],
-	[3, 0, 1, 2], [3, 1, 2, 0], [3, 2, 0, 1], [3, 0, 2, 1], [3, 2, 1, 0], [3, 1, 0, 2]];
+/// The lines that are inserted into the (folded) input burrow for part 2.
+const BURROW_FOLD: &str = concat!(
+	"  #D#C#B#A#\n",
+	"  #D#B#A#C#\n");
 
 
+/// `.0` is the kind’s index in `AmphipodKinds` (which is also its target side room’s index).
 #[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
-enum AmphipodKind { Amber, Bronze, Copper, Desert }
+struct AmphipodKind(usize);
+
+/// Per amphipod kind, its letter and step cost.
+#[cfg_attr(test, derive(Debug))]
+#[derive(Clone)]
+struct AmphipodKinds(Vec<(char, u32)>);
+
+impl Default for AmphipodKinds {
+	fn default() -> Self {
+		AmphipodKinds(vec![('A', 1), ('B', 10), ('C', 100), ('D', 1000)])
+	}
+}
+
+impl AmphipodKinds {
+	fn len(&self) -> usize {
+		self.0.len()
+	}
+
+	fn iter(&self) -> impl Iterator<Item = AmphipodKind> {
+		(0..self.0.len()).map(AmphipodKind)
+	}
+
+	fn find(&self, letter: char) -> Option<AmphipodKind> {
+		self.0.iter().position(|&(l, _)| l == letter).map(AmphipodKind)
+	}
+
+	fn letter(&self, kind: AmphipodKind) -> char {
+		self.0[kind.0].0
+	}
+
+	fn step_cost(&self, kind: AmphipodKind) -> u64 {
+		self.0[kind.0].1.into()
+	}
+}
+
+/// The graph of spaces in a burrow, derived from its map; spaces are numbered in
+/// reading order of the map (i.e. hallway first, then side rooms line by line).
+#[cfg_attr(test, derive(Debug))]
+struct BurrowGeometry {
+	kinds: AmphipodKinds,
+	/// The map, with amphipods replaced by open spaces.
+	map: Vec<String>,
+	/// Per space, its `[column, line]` location in the map.
+	locs: Vec<[usize; 2]>,
+	/// Per space, the spaces one step away (in up, right, down & left order).
+	steps: Vec<Vec<usize>>,
+	/// Per space, the index of the side room it’s in (or `None` if it’s in the hallway).
+	space_rooms: Vec<Option<usize>>,
+	/// Per side room, its target amphipod kind and its spaces (from the entrance inwards).
+	rooms: Vec<(AmphipodKind, Vec<usize>)>,
+	/// Per space, whether it’s right outside a side room.
+	outside_side_room: Vec<bool>,
+	/// Per pair of spaces, the number of steps between them.
+	step_counts: Vec<Vec<usize>>,
+}
+
+impl BurrowGeometry {
+	fn room_depth(&self) -> usize {
+		self.rooms[0].1.len()
+	}
 
-/// `.0` is the location’s index in `BURROW_LOCS`.
-type BurrowAmphipods<const N: usize> = [(usize, AmphipodKind); N];
+	/// The number of bits needed per space to pack its occupancy (empty, or one of the kinds).
+	fn packing_bits(&self) -> u32 {
+		usize::BITS - self.kinds.len().leading_zeros()
+	}
+
+	/// Packs the occupancy of every space, so that equivalent amphipods (i.e. of the same kind,
+	/// swapped around) pack equally, and a lot more compactly than `BurrowAmphipods`.
+	fn packed(&self, amphipods: &[(usize, AmphipodKind)]) -> PackedAmphipods {
+		let bits = self.packing_bits();
+		if self.locs.len() * bits as usize <= u128::BITS as usize {
+			return PackedAmphipods::Narrow(amphipods.iter()
+				.fold(0, |packed, &(space, kind)| packed | (kind.0 as u128 + 1) << (space as u32 * bits)))
+		}
+
+		// Not letting spaces straddle words
+		let spaces_per_word = (u64::BITS / bits) as usize;
+		let mut words = vec![0u64; self.locs.len().div_ceil(spaces_per_word)];
+		for &(space, kind) in amphipods {
+			words[space / spaces_per_word] |= (kind.0 as u64 + 1) << ((space % spaces_per_word) as u32 * bits);
+		}
+		PackedAmphipods::Wide(words.into_boxed_slice())
+	}
+}
+
+/// `.0` is the space’s index in `BurrowGeometry::locs`.
+type BurrowAmphipods = Vec<(usize, AmphipodKind)>;
+
+/// See `BurrowGeometry::packed`; burrows with too many spaces for 128 bits are packed into as many words as needed.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+enum PackedAmphipods {
+	Narrow(u128),
+	Wide(Box<[u64]>),
+}
 
 #[cfg_attr(test, derive(Debug))]
-struct Burrow<const NS: usize, const NA: usize> {
-	amphipods: BurrowAmphipods<NA>
+#[derive(Clone)]
+struct Burrow {
+	geometry: Rc<BurrowGeometry>,
+	amphipods: BurrowAmphipods,
+}
+
+/// `from` and `to` are the spaces’ indices in `BurrowGeometry::locs`; `cost` is the energy the move takes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct Move {
+	kind: AmphipodKind,
+	from: usize,
+	to: usize,
+	cost: u64,
 }
 
 
 mod moves {
 	use std::{collections::{VecDeque, HashSet}, iter, rc::Rc};
 	use itertools::Itertools;
-	use super::{
-		BURROW_FOLDED_NUM_SPACES,
-		BURROW_ALL_LOCS,
-		BURROW_FOLDED_STEPS, BURROW_UNFOLDED_STEPS,
-		BURROW_FOLDED_SPACE_STEPS, BURROW_UNFOLDED_SPACE_STEPS,
-		BURROW_HALLWAY_SPACES, BURROW_OUTSIDE_SIDE_ROOM_SPACES,
-		BURROW_TARGET_ROOMS,
-		AmphipodKind, Burrow};
-
-	struct BurrowState<const NS: usize> {
-		space_amphipods: [Option<AmphipodKind>; NS],
-	}
-
-	impl AmphipodKind {
-		pub(super) fn step_cost(&self) -> u32 {
-			use AmphipodKind::*;
-			match self {
-				Amber => 1,
-				Bronze => 10,
-				Copper => 100,
-				Desert => 1000,
-			}
-		}
+	use super::{AmphipodKind, BurrowGeometry};
+	#[cfg(test)]
+	use super::Burrow;
+
+	struct BurrowState {
+		space_amphipods: Vec<Option<AmphipodKind>>,
 	}
 
-	impl<const NS: usize, const NA: usize> Burrow<NS, NA> {
-		fn state(&self) -> BurrowState<NS> {
-			let mut space_amphipods = [None; NS];
-			for (space, amphipod) in self.amphipods {
+	impl BurrowGeometry {
+		fn state(&self, amphipods: &[(usize, AmphipodKind)]) -> BurrowState {
+			let mut space_amphipods = vec![None; self.locs.len()];
+			for &(space, amphipod) in amphipods {
 				space_amphipods[space] = Some(amphipod);
 			}
 			BurrowState { space_amphipods }
 		}
 
-		fn steps(from: usize) -> impl Iterator<Item = usize> {
-			if NS == BURROW_FOLDED_NUM_SPACES {
-				BURROW_FOLDED_STEPS[BURROW_FOLDED_SPACE_STEPS[from]..BURROW_FOLDED_SPACE_STEPS[from + 1]].iter().copied()
-			} else {
-				BURROW_UNFOLDED_STEPS[BURROW_UNFOLDED_SPACE_STEPS[from]..BURROW_UNFOLDED_SPACE_STEPS[from + 1]].iter().copied()
-			}
-		}
-
-		fn in_filled_spaces_of_target_room(amphipod: AmphipodKind, at: usize, state: &BurrowState<NS>) -> bool {
-			if BURROW_HALLWAY_SPACES.contains(&at) { return false }
-			let &(target, ref spaces) =
-				&BURROW_TARGET_ROOMS[(at - BURROW_HALLWAY_SPACES.end) % BURROW_TARGET_ROOMS.len()];
+		fn in_filled_spaces_of_target_room(&self, amphipod: AmphipodKind, at: usize, state: &BurrowState) -> bool {
+			let Some(room) = self.space_rooms[at] else { return false };
+			let (target, ref spaces) = self.rooms[room];
 			if amphipod != target { return false }
-			spaces[0..NA / 4].iter().rev().copied()
+			spaces.iter().rev().copied()
 				.take_while(move |s| state.space_amphipods[*s] == Some(amphipod))
 				.contains(&at)
 		}
 
-		fn entering_unavailable_target_room_from_hallway(amphipod: AmphipodKind, from: usize, to: usize, state: &BurrowState<NS>) -> bool {
-			if !BURROW_HALLWAY_SPACES.contains(&from) || BURROW_HALLWAY_SPACES.contains(&to) { return false }
-			let &(target, ref spaces) =
-				&BURROW_TARGET_ROOMS[(to - BURROW_HALLWAY_SPACES.end) % BURROW_TARGET_ROOMS.len()];
+		fn entering_unavailable_target_room_from_hallway(&self, amphipod: AmphipodKind, from: usize, to: usize, state: &BurrowState) -> bool {
+			if self.space_rooms[from].is_some() { return false }
+			let Some(room) = self.space_rooms[to] else { return false };
+			let (target, ref spaces) = self.rooms[room];
 			assert_eq!(to, spaces[0]);
 			if amphipod != target { return true }
-			spaces[0..NA / 4].iter().rev().copied()
+			spaces.iter().rev().copied()
 				.skip_while(move |s| state.space_amphipods[*s] == Some(target))
 				.any(|s| state.space_amphipods[s].is_some())
 		}
 
 		/// Assuming neither `initial_from` nor `to` in hallway
-		fn same_target_room(initial_from: usize, to: usize) -> bool {
-			BURROW_ALL_LOCS[initial_from][0] == BURROW_ALL_LOCS[to][0]
+		fn same_target_room(&self, initial_from: usize, to: usize) -> bool {
+			self.space_rooms[initial_from] == self.space_rooms[to]
 		}
 
 		/// Assumes this is the right target room
-		fn in_target_room_but_not_deepest(to: usize, state: &BurrowState<NS>) -> bool {
-			if BURROW_HALLWAY_SPACES.contains(&to) { return false }
-			let &(_, ref spaces) =
-				&BURROW_TARGET_ROOMS[(to - BURROW_HALLWAY_SPACES.end) % BURROW_TARGET_ROOMS.len()];
-			spaces[0..NA / 4].iter().rev().copied()
+		fn in_target_room_but_not_deepest(&self, to: usize, state: &BurrowState) -> bool {
+			let Some(room) = self.space_rooms[to] else { return false };
+			self.rooms[room].1.iter().rev().copied()
 				.skip_while(move |s| state.space_amphipods[*s].is_some())
 				.skip(1)
 				.contains(&to)
 		}
 
-		fn amphipod_moves<'a>(&'a self, amphipod: usize, state: Option<&'_ Rc<BurrowState<NS>>>) -> impl Iterator<Item = (usize, u32)> + 'a {
+		fn amphipod_moves<'a>(&'a self, amphipods: &[(usize, AmphipodKind)], amphipod: usize, state: Option<&'_ Rc<BurrowState>>) -> impl Iterator<Item = (usize, u64)> + 'a {
 			let a = amphipod;
-			let (initial_from, amphipod) = self.amphipods[a];
-			let initial_from_hallway = BURROW_HALLWAY_SPACES.contains(&initial_from);
-			let step_cost = amphipod.step_cost();
+			let (initial_from, amphipod) = amphipods[a];
+			let initial_from_hallway = self.space_rooms[initial_from].is_none();
+			let step_cost = self.kinds.step_cost(amphipod);
 
-			let state = state.cloned().unwrap_or_else(|| Rc::new(self.state()));
+			let state = state.cloned().unwrap_or_else(|| Rc::new(self.state(amphipods)));
 
-			let mut seen = HashSet::with_capacity(NS);
+			let mut seen = HashSet::with_capacity(self.locs.len());
 			let mut queue = VecDeque::from([(None, initial_from, 0)]);
 			iter::from_fn(move || {
 				while let Some((from, to, cost)) = queue.pop_front() {
 					if !seen.insert(to) { continue }
 
-					if to == initial_from && Self::in_filled_spaces_of_target_room(amphipod, to, state.as_ref()) {
+					if to == initial_from && self.in_filled_spaces_of_target_room(amphipod, to, state.as_ref()) {
 						return None
 					}
 
@@ -211,7 +202,7 @@ mod moves {
 
 						// Can’t step from hallway into side room that’s not this amphipod’s target room,
 						// or that’s not the target room of any contains amphipods it already contains.
-						if Self::entering_unavailable_target_room_from_hallway(amphipod, from, to, state.as_ref()) {
+						if self.entering_unavailable_target_room_from_hallway(amphipod, from, to, state.as_ref()) {
 							continue
 						}
 					}
@@ -219,24 +210,24 @@ mod moves {
 					// Enqueue next steps from this step
 					{
 						let from = to;
-						queue.extend(Self::steps(from).map(|to| (Some(from), to, cost + step_cost)));
+						queue.extend(self.steps[from].iter().map(|&to| (Some(from), to, cost + step_cost)));
 					}
 
 					if to == initial_from { continue }
 
-					let to_hallway = BURROW_HALLWAY_SPACES.contains(&to);
+					let to_hallway = self.space_rooms[to].is_none();
 
 					// Don’t stop if initially in hallway and not moving into target room
 					if initial_from_hallway && to_hallway { continue }
 
 					// Don’t linger in the room where we started
-					if !initial_from_hallway && !to_hallway && Self::same_target_room(initial_from, to) { continue }
+					if !initial_from_hallway && !to_hallway && self.same_target_room(initial_from, to) { continue }
 
 					// Don’t stop right outside a side room
-					if BURROW_OUTSIDE_SIDE_ROOM_SPACES.iter().contains(&to) { continue }
+					if self.outside_side_room[to] { continue }
 
 					// Don’t stop halfway into the target room
-					if Self::in_target_room_but_not_deepest(to, state.as_ref()) { continue }
+					if self.in_target_room_but_not_deepest(to, state.as_ref()) { continue }
 
 					return Some((to, cost))
 				}
@@ -244,53 +235,62 @@ mod moves {
 			})
 		}
 
-		pub(super) fn all_moves(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
-			let state = Rc::new(self.state());
-			(0..self.amphipods.len())
-				.flat_map(move |amphipod| self.amphipod_moves(amphipod, Some(&state))
+		pub(super) fn all_moves<'a>(&'a self, amphipods: &'a [(usize, AmphipodKind)]) -> impl Iterator<Item = (usize, usize, u64)> + 'a {
+			let state = Rc::new(self.state(amphipods));
+			(0..amphipods.len())
+				.flat_map(move |amphipod| self.amphipod_moves(amphipods, amphipod, Some(&state))
 					.map(move |(space, cost)| (amphipod, space, cost)))
 		}
 	}
 
+	#[cfg(test)]
+	impl Burrow {
+		fn amphipod_moves(&self, amphipod: usize) -> impl Iterator<Item = (usize, u64)> + '_ {
+			self.geometry.amphipod_moves(&self.amphipods, amphipod, None)
+		}
+
+		fn all_moves(&self) -> impl Iterator<Item = (usize, usize, u64)> + '_ {
+			self.geometry.all_moves(&self.amphipods)
+		}
+	}
+
 	#[test]
 	fn test() -> Result<(), super::parsing::BurrowError> {
-		const NS: usize = BURROW_FOLDED_NUM_SPACES;
-		const NA: usize = super::BURROW_FOLDED_NUM_AMPHIPODS;
-		itertools::assert_equal(Burrow::<NS, NA>::steps(11), [2, 15]);
-		let mut burrow = super::TEST_INPUT.parse::<Burrow<NS, NA>>()?;
+		let mut burrow = super::TEST_INPUT.parse::<Burrow>()?;
+		itertools::assert_equal(&burrow.geometry.steps[11], &[2, 15]);
 		itertools::assert_equal(burrow.all_moves(), [
 			(0, 3, 20), (0, 1, 20), (0, 0, 30), (0, 5, 40), (0, 7, 60), (0, 9, 80), (0, 10, 90),
 			(1, 5, 200), (1, 3, 200), (1, 7, 400), (1, 1, 400), (1, 0, 500), (1, 9, 600), (1, 10, 700),
 			(2, 7, 20), (2, 5, 20), (2, 9, 40), (2, 3, 40), (2, 10, 50), (2, 1, 60), (2, 0, 70),
 			(3, 9, 2000), (3, 7, 2000), (3, 10, 3000), (3, 5, 4000), (3, 3, 6000), (3, 1, 8000), (3, 0, 9000)]);
 		burrow.amphipods[2].0 = 3;
-		itertools::assert_equal(burrow.amphipod_moves(2, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(1, None), [(5, 200), (7, 400), (13, 400), (9, 600), (10, 700)]);
+		itertools::assert_equal(burrow.amphipod_moves(2), []);
+		itertools::assert_equal(burrow.amphipod_moves(1), [(5, 200), (7, 400), (13, 400), (9, 600), (10, 700)]);
 		burrow.amphipods[1].0 = 13;
-		itertools::assert_equal(burrow.amphipod_moves(1, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(6, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(2, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(5, None), [(5, 3000), (7, 5000), (9, 7000), (10, 8000)]);
+		itertools::assert_equal(burrow.amphipod_moves(1), []);
+		itertools::assert_equal(burrow.amphipod_moves(6), []);
+		itertools::assert_equal(burrow.amphipod_moves(2), []);
+		itertools::assert_equal(burrow.amphipod_moves(5), [(5, 3000), (7, 5000), (9, 7000), (10, 8000)]);
 		burrow.amphipods[5].0 = 5;
-		itertools::assert_equal(burrow.amphipod_moves(5, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(2, None), [(16, 30)]);
+		itertools::assert_equal(burrow.amphipod_moves(5), []);
+		itertools::assert_equal(burrow.amphipod_moves(2), [(16, 30)]);
 		burrow.amphipods[2].0 = 16;
-		itertools::assert_equal(burrow.amphipod_moves(0, None), [(3, 20), (1, 20), (0, 30), (12, 40)]);
+		itertools::assert_equal(burrow.amphipod_moves(0), [(3, 20), (1, 20), (0, 30), (12, 40)]);
 		burrow.amphipods[0].0 = 12;
 		// TODO(bm-w): `itertools::assert_equal(burrow.moves(2), []);`
-		itertools::assert_equal(burrow.amphipod_moves(3, None), [(9, 2000), (7, 2000), (10, 3000)]);
+		itertools::assert_equal(burrow.amphipod_moves(3), [(9, 2000), (7, 2000), (10, 3000)]);
 		burrow.amphipods[3].0 = 7;
-		itertools::assert_equal(burrow.amphipod_moves(3, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(7, None), [(9, 3), (10, 4)]);
+		itertools::assert_equal(burrow.amphipod_moves(3), []);
+		itertools::assert_equal(burrow.amphipod_moves(7), [(9, 3), (10, 4)]);
 		burrow.amphipods[7].0 = 9;
-		itertools::assert_equal(burrow.amphipod_moves(7, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(3, None), [(18, 3000)]);
+		itertools::assert_equal(burrow.amphipod_moves(7), []);
+		itertools::assert_equal(burrow.amphipod_moves(3), [(18, 3000)]);
 		burrow.amphipods[3].0 = 18;
-		itertools::assert_equal(burrow.amphipod_moves(5, None), [(14, 4000)]);
+		itertools::assert_equal(burrow.amphipod_moves(5), [(14, 4000)]);
 		burrow.amphipods[5].0 = 14;
-		itertools::assert_equal(burrow.amphipod_moves(3, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(5, None), []);
-		itertools::assert_equal(burrow.amphipod_moves(7, None), [(11, 8)]);
+		itertools::assert_equal(burrow.amphipod_moves(3), []);
+		itertools::assert_equal(burrow.amphipod_moves(5), []);
+		itertools::assert_equal(burrow.amphipod_moves(7), [(11, 8)]);
 		burrow.amphipods[7].0 = 11;
 		itertools::assert_equal(burrow.all_moves(), []);
 		Ok(())
@@ -299,296 +299,707 @@ mod moves {
 
 
 mod organization {
-	use std::collections::{BinaryHeap, HashMap, hash_map::Entry};
-	use super::{
-		BURROW_FOLDED_NUM_AMPHIPODS,
-		BURROW_TARGET_ROOMS, BURROW_MOVE_STEP_COUNTS,
-		BURROW_FOLDED_TARGET_ROOM_SPACE_PERMUTATIONS, BURROW_UNFOLDED_TARGET_ROOM_SPACE_PERMUTATIONS,
-		Burrow, BurrowAmphipods, AmphipodKind};
+	use std::{collections::{BinaryHeap, HashMap, hash_map::Entry}, hash::Hash};
+	use super::{Burrow, BurrowGeometry, BurrowAmphipods, PackedAmphipods, AmphipodKind, Move};
+	#[cfg(test)]
+	use itertools::Itertools as _;
 
+	#[allow(dead_code)]
+	#[derive(Clone, Copy)]
 	#[cfg_attr(test, derive(Debug))]
-	#[derive(PartialEq, Eq)]
-	struct BurrowState<const NA: usize>{
-		unorganizedness: Option<u32>,
-		cost: u32,
-		tiebreaker: usize,
-		amphipods: BurrowAmphipods<NA>,
+	pub(super) enum Search {
+		/// A* with the `unorganizedness` heuristic.
+		AStar,
+		/// Uniform-cost search (i.e. A* without heuristic).
+		Dijkstra,
+		/// Iterative deepening A* with the `unorganizedness` heuristic; only needs memory for the
+		/// current path, but revisits states a lot (so it’s only practical for small burrows).
+		IdaStar,
 	}
 
-	impl<const NA: usize> BurrowState<NA> {
-		fn heuristic(&self) -> u32 {
-			self.cost + self.unorganizedness.unwrap_or(0)
-		}
+	#[derive(Debug, Default, PartialEq, Eq)]
+	pub(super) struct SearchStats {
+		/// The number of states whose moves were explored.
+		pub(super) expanded: usize,
+		/// The maximum size of the heap (or for IDA*, the maximum length of the path).
+		pub(super) max_heap_len: usize,
+		/// The size of the `seen` map when the search finished (always zero for IDA*).
+		pub(super) seen_len: usize,
+	}
+
+	#[cfg_attr(test, derive(Debug))]
+	#[derive(PartialEq, Eq)]
+	struct BurrowState {
+		unorganizedness: Option<u64>,
+		cost: u64,
+		/// `cost` plus the estimated remaining cost (zero for Dijkstra).
+		heuristic: u64,
+		tiebreaker: usize,
+		amphipods: BurrowAmphipods,
 	}
 
-	impl<const NA: usize> PartialOrd for BurrowState<NA> {
+	impl PartialOrd for BurrowState {
 		fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
 			Some(self.cmp(other))
 		}
 	}
 
-	impl<const NA: usize> Ord for BurrowState<NA> {
+	impl Ord for BurrowState {
 		fn cmp(&self, other: &Self) -> std::cmp::Ordering {
 			// Inversely comparing cost so that lowest heuristic puts `self` at the top of a `BinaryHeap`
-			other.heuristic().cmp(&self.heuristic())
+			other.heuristic.cmp(&self.heuristic)
 				.then(self.tiebreaker.cmp(&other.tiebreaker))
 		}
 	}
 
-	impl<const NA: usize> BurrowState<NA> {
-		fn unorganizedness(amphipods: &BurrowAmphipods<NA>) -> Option<u32> {
+	impl BurrowState {
+		fn unorganizedness(geometry: &BurrowGeometry, amphipods: &[(usize, AmphipodKind)]) -> Option<u64> {
 			let amphipods = {
-				let mut copied = *amphipods;
-				copied.sort_by(|&(_, la), (_, ra)| la.cmp(ra));
+				let mut copied = amphipods.to_vec();
+				copied.sort_by_key(|&(_, a)| a);
 				copied
 			};
 
-			let permutated_steps_count = |spaces: &[usize], amphipods: &[(usize, AmphipodKind)], a0: usize| {
-				if NA == BURROW_FOLDED_NUM_AMPHIPODS {
-					BURROW_FOLDED_TARGET_ROOM_SPACE_PERMUTATIONS.iter()
-						.map(|permutated_idxs|
-							permutated_idxs.iter().enumerate()
-								.map(|(a, &s)| BURROW_MOVE_STEP_COUNTS[spaces[s]][amphipods[a0 + a].0])
-								.sum::<usize>())
-						.min().unwrap()
-				} else {
-					BURROW_UNFOLDED_TARGET_ROOM_SPACE_PERMUTATIONS.iter()
-						.map(|permutated_idxs|
-							permutated_idxs.iter().enumerate()
-								.map(|(a, &s)| BURROW_MOVE_STEP_COUNTS[spaces[s]][amphipods[a0 + a].0])
-								.sum::<usize>())
-						.min().unwrap()
-				}
+			// Every path into a side room passes its entrance, so distances to its spaces are distances along
+			// a line; amphipods placed on that line (negative outside the room) are optimally matched by order
+			let matched_steps_count = |spaces: &[usize], amphipods: &[(usize, AmphipodKind)]| {
+				let mut offsets = amphipods.iter()
+					.map(|&(space, _)| match spaces.iter().position(|&s| s == space) {
+						Some(d) => d as isize,
+						None => -(geometry.step_counts[spaces[0]][space] as isize),
+					})
+					.collect::<Vec<_>>();
+				offsets.sort_unstable();
+				offsets.into_iter().enumerate().map(|(d, o)| (o - d as isize).unsigned_abs()).sum::<usize>()
 			};
 
-			// Basically underestimated hamming distance around the room entrances (considering step costs)
-			let unorganizedness = BURROW_TARGET_ROOMS.iter().enumerate().map(|(r, (target, spaces))| {
-				target.step_cost() * permutated_steps_count(&spaces[0..NA / 4], &amphipods[..], r * NA / 4) as u32
-			}).sum();
+			// Basically underestimated hamming distance around the room entrances (considering step costs);
+			// saturating, as that still underestimates (and no organization can cost more anyway)
+			let depth = geometry.room_depth();
+			let unorganizedness = geometry.rooms.iter().enumerate().map(|(r, (target, spaces))| {
+				geometry.kinds.step_cost(*target).saturating_mul(matched_steps_count(spaces, &amphipods[r * depth..(r + 1) * depth]) as u64)
+			}).fold(0, u64::saturating_add);
 
 			if unorganizedness > 0 { Some(unorganizedness) } else { None }
 		}
 
-		fn with_move(amphipods: &BurrowAmphipods<NA>, amphipod: usize, space: usize, cost: u32, tiebreaker: usize) -> BurrowState<NA> {
-			let mut amphipods = *amphipods;
-			amphipods[amphipod].0 = space;
-			BurrowState { unorganizedness: BurrowState::unorganizedness(&amphipods), cost, tiebreaker, amphipods }
+		/// Returns `None` if organizing from this state would cost more than `u64::MAX`.
+		fn new(geometry: &BurrowGeometry, search: Search, amphipods: BurrowAmphipods, cost: u64, tiebreaker: usize) -> Option<BurrowState> {
+			let unorganizedness = BurrowState::unorganizedness(geometry, &amphipods);
+			let heuristic = match search {
+				Search::Dijkstra => cost,
+				Search::AStar | Search::IdaStar => cost.checked_add(unorganizedness.unwrap_or(0))?,
+			};
+			Some(BurrowState { unorganizedness, cost, heuristic, tiebreaker, amphipods })
+		}
+	}
+
+	fn with_move(amphipods: &BurrowAmphipods, amphipod: usize, space: usize) -> BurrowAmphipods {
+		let mut amphipods = amphipods.clone();
+		amphipods[amphipod].0 = space;
+		amphipods
+	}
+
+	enum IdaStarResult { Found(u64), Exceeded(u64), Exhausted }
+
+	/// Depth-first searches from the last of `path`, up to `bound`, returning the cost if it’s organized,
+	/// or the least heuristic that exceeded `bound`; `moves` is left as the organizing moves if found.
+	fn ida_star(geometry: &BurrowGeometry, path: &mut Vec<BurrowAmphipods>, packed_path: &mut Vec<PackedAmphipods>, moves: &mut Vec<Move>, cost: u64, bound: u64, stats: &mut SearchStats) -> IdaStarResult {
+		use IdaStarResult::*;
+		stats.max_heap_len = stats.max_heap_len.max(path.len());
+
+		let amphipods = path.last().unwrap();
+		let unorganizedness = BurrowState::unorganizedness(geometry, amphipods);
+		let Some(heuristic) = cost.checked_add(unorganizedness.unwrap_or(0)) else { return Exhausted };
+		if heuristic > bound { return Exceeded(heuristic) }
+		if unorganizedness.is_none() { return Found(cost) }
+
+		stats.expanded += 1;
+		let mut least_exceeded = None;
+		for (amphipod, space, add_cost) in geometry.all_moves(amphipods).collect::<Vec<_>>() {
+			let Some(next_cost) = cost.checked_add(add_cost) else { continue };
+			let amphipods = path.last().unwrap();
+			let next_amphipods = with_move(amphipods, amphipod, space);
+			let next_packed = geometry.packed(&next_amphipods);
+			if packed_path.contains(&next_packed) { continue }
+
+			let (from, kind) = amphipods[amphipod];
+			path.push(next_amphipods);
+			packed_path.push(next_packed);
+			moves.push(Move { kind, from, to: space, cost: add_cost });
+			match ida_star(geometry, path, packed_path, moves, next_cost, bound, stats) {
+				Found(cost) => return Found(cost),
+				Exceeded(heuristic) => least_exceeded = Some(least_exceeded.map_or(heuristic, |l: u64| l.min(heuristic))),
+				Exhausted => (),
+			}
+			path.pop();
+			packed_path.pop();
+			moves.pop();
 		}
+		least_exceeded.map_or(Exhausted, Exceeded)
 	}
 
-	impl<const NS: usize, const NA: usize> Burrow<NS, NA> {
-		pub(super) fn organization_cost(&self) -> Option<u32> {
+	impl Burrow {
+		pub(super) fn organization_cost(&self) -> Option<u64> {
+			self.organization().map(|(cost, _)| cost)
+		}
+
+		/// Returns the least total cost, and the moves that achieve it (or `None` if the
+		/// burrow can’t be organized, at least not for less than `u64::MAX`).
+		pub(super) fn organization(&self) -> Option<(u64, Vec<Move>)> {
+			self.organization_with(Search::AStar).0
+		}
+
+		/// Like `organization`, but using the given `search` algorithm, and also returning statistics about it.
+		pub(super) fn organization_with(&self, search: Search) -> (Option<(u64, Vec<Move>)>, SearchStats) {
+			let geometry = self.geometry.as_ref();
+			let mut stats = SearchStats::default();
+
+			if let Search::IdaStar = search {
+				let mut path = vec![self.amphipods.clone()];
+				let mut packed_path = vec![geometry.packed(&self.amphipods)];
+				let mut moves = Vec::new();
+				let mut bound = BurrowState::unorganizedness(geometry, &self.amphipods).unwrap_or(0);
+				loop {
+					match ida_star(geometry, &mut path, &mut packed_path, &mut moves, 0, bound, &mut stats) {
+						IdaStarResult::Found(cost) => return (Some((cost, moves)), stats),
+						IdaStarResult::Exceeded(heuristic) => bound = heuristic,
+						IdaStarResult::Exhausted => return (None, stats),
+					}
+				}
+			}
+
+			self.searched_by_key(search, stats, |amphipods| geometry.packed(amphipods))
+		}
+
+		/// Runs an A* (or Dijkstra) search, keeping track of seen states by their `key`.
+		fn searched_by_key<K: Hash + Eq + Clone>(&self, search: Search, mut stats: SearchStats, key: impl Fn(&BurrowAmphipods) -> K)
+		-> (Option<(u64, Vec<Move>)>, SearchStats) {
+			let geometry = self.geometry.as_ref();
 			let mut seen = HashMap::new();
+			let mut came_from = HashMap::<K, (K, Move)>::new();
 			let mut tiebreaker = 0;
-			let mut heap = BinaryHeap::from([BurrowState::<NA> {
-				unorganizedness: BurrowState::unorganizedness(&self.amphipods),
-				cost: 0,
-				tiebreaker,
-				amphipods: self.amphipods
-			}]);
+			let mut heap = BinaryHeap::from_iter(BurrowState::new(geometry, search, self.amphipods.clone(), 0, tiebreaker));
+			stats.max_heap_len = heap.len();
 
 			while let Some(burrow_state) = heap.pop() {
 				tiebreaker += 1;
+				let burrow_key = key(&burrow_state.amphipods);
 
 				if burrow_state.unorganizedness.is_none() {
-					return Some(burrow_state.cost)
+					let mut moves = Vec::new();
+					let mut key = &burrow_key;
+					while let Some((prev_key, mov)) = came_from.get(key) {
+						moves.push(*mov);
+						key = prev_key;
+					}
+					moves.reverse();
+					stats.seen_len = seen.len();
+					return (Some((burrow_state.cost, moves)), stats)
 				}
 
-				if match seen.entry(burrow_state.amphipods) {
-					Entry::Occupied(entry) => *entry.get() < burrow_state.heuristic(),
-					Entry::Vacant(entry) => { entry.insert(burrow_state.heuristic()); false },
+				if match seen.entry(burrow_key.clone()) {
+					Entry::Occupied(entry) => *entry.get() < burrow_state.heuristic,
+					Entry::Vacant(entry) => { entry.insert(burrow_state.heuristic); false },
 				} {
 					continue
 				}
 
+				stats.expanded += 1;
 
-				for (amphipod, space, add_cost) in (Burrow::<NS, NA> { amphipods: burrow_state.amphipods }).all_moves() {
-					let next_state = BurrowState::with_move(&burrow_state.amphipods, amphipod, space,
-						burrow_state.cost + add_cost, tiebreaker);
-					let next_heuristic = next_state.heuristic();
+				for (amphipod, space, add_cost) in geometry.all_moves(&burrow_state.amphipods) {
+					let Some(next_state) = burrow_state.cost.checked_add(add_cost)
+						.and_then(|cost| BurrowState::new(geometry, search, with_move(&burrow_state.amphipods, amphipod, space), cost, tiebreaker))
+						else { continue };
+					let next_heuristic = next_state.heuristic;
+					let next_key = key(&next_state.amphipods);
 
-					let (inserted, known_heuristic) = match seen.entry(next_state.amphipods) {
+					let (inserted, known_heuristic) = match seen.entry(next_key.clone()) {
 						Entry::Occupied(entry) => (false, entry.into_mut()),
 						Entry::Vacant(entry) => (true, entry.insert(next_heuristic)),
 					};
 					if !inserted && *known_heuristic <= next_heuristic { continue }
 					*known_heuristic = next_heuristic;
 
+					let (from, kind) = burrow_state.amphipods[amphipod];
+					came_from.insert(next_key, (burrow_key.clone(), Move { kind, from, to: space, cost: add_cost }));
 					heap.push(next_state);
 				}
+				stats.max_heap_len = stats.max_heap_len.max(heap.len());
 			}
 
-			None
+			stats.seen_len = seen.len();
+			(None, stats)
 		}
 	}
 
 	#[test]
 	fn test() -> Result<(), super::parsing::BurrowError> {
-		use super::AmphipodKind::*;
-		assert_eq!(BurrowState::unorganizedness(&[(11, Amber), (12, Bronze), (13, Copper), (14, Desert), (15, Amber), (16, Bronze), (17, Copper), (18, Desert)]), None);
+		let mut burrow = super::TEST_INPUT.parse::<Burrow>()?;
+		let [a, b, c, d] = [0, 1, 2, 3].map(AmphipodKind);
+		assert_eq!(BurrowState::unorganizedness(&burrow.geometry, &[(11, a), (12, b), (13, c), (14, d), (15, a), (16, b), (17, c), (18, d)]), None);
+		assert_eq!(burrow.geometry.packed(&[(11, a), (15, a), (0, b)]), burrow.geometry.packed(&[(0, b), (15, a), (11, a)]));
+		assert_ne!(burrow.geometry.packed(&[(11, a), (15, b)]), burrow.geometry.packed(&[(11, b), (15, a)]));
+
+		// Too many spaces for 128 bits (3 bits per space for 6 kinds, 11 + 6 × 5 spaces)
+		let kinds = "A=1 B=2 C=3 D=4 E=5 F=6".parse().unwrap();
+		let wide = Burrow::from_str_with_kinds(&format!("#...............#\n###F#E#D#C#B#A###{}", "\n  #A#B#C#D#E#F#".repeat(4)), kinds)?;
+		let [e, f] = [4, 5].map(AmphipodKind);
+		assert!(matches!(wide.geometry.packed(&wide.amphipods), PackedAmphipods::Wide(words) if words.len() == 3));
+		assert_eq!(wide.geometry.packed(&[(0, f), (40, e), (21, f)]), wide.geometry.packed(&[(21, f), (0, f), (40, e)]));
+		assert_ne!(wide.geometry.packed(&[(20, e), (21, f)]), wide.geometry.packed(&[(20, f), (21, e)]));
+
+		// The heuristic must never overestimate the remaining cost along the optimal moves
+		let (cost, moves) = burrow.organization().unwrap();
+		for (i, replayed) in burrow.replay(&moves).enumerate() {
+			let remaining_cost = cost - moves[..i].iter().map(|m| m.cost).sum::<u64>();
+			assert!(BurrowState::unorganizedness(&burrow.geometry, &replayed.amphipods).unwrap_or(0) <= remaining_cost);
+
+			// Matching amphipods to room spaces by order is as good as trying every assignment
+			let mut amphipods = replayed.amphipods.to_vec();
+			amphipods.sort_by_key(|&(_, a)| a);
+			let depth = burrow.geometry.room_depth();
+			let exhaustive = burrow.geometry.rooms.iter().zip(amphipods.chunks(depth)).map(|((target, spaces), amphipods)| {
+				burrow.geometry.kinds.step_cost(*target) * spaces.iter().permutations(depth)
+					.map(|spaces| Iterator::zip(spaces.into_iter(), amphipods)
+						.map(|(&s, &(a, _))| burrow.geometry.step_counts[s][a] as u64).sum::<u64>())
+					.min().unwrap()
+			}).sum::<u64>();
+			assert_eq!(BurrowState::unorganizedness(&burrow.geometry, &replayed.amphipods).unwrap_or(0), exhaustive);
+		}
+
+		let (a_star, a_star_stats) = burrow.organization_with(Search::AStar);
+		let (dijkstra, dijkstra_stats) = burrow.organization_with(Search::Dijkstra);
+		assert_eq!(a_star.map(|(cost, _)| cost), Some(12521));
+		assert_eq!(dijkstra.map(|(cost, _)| cost), Some(12521));
+		assert!(a_star_stats.expanded < dijkstra_stats.expanded);
+		assert!(a_star_stats.seen_len < dijkstra_stats.seen_len);
+		assert!(a_star_stats.max_heap_len > 0 && a_star_stats.max_heap_len <= a_star_stats.seen_len);
 
-		const NS: usize = super::BURROW_FOLDED_NUM_SPACES;
-		const NA: usize = super::BURROW_FOLDED_NUM_AMPHIPODS;
-		let mut burrow = super::TEST_INPUT.parse::<Burrow<NS, NA>>()?;
 		// Just testing the last few steps (the full test is below in `day23::test`)
 		for (amphipod, space) in [(2, 3), (1, 13), (5, 5), (2, 16), (0, 12), (3, 7), (7, 9), (3, 18)] {
 			burrow.amphipods[amphipod].0 = space;
 		}
 		assert_eq!(burrow.organization_cost(), Some(4008));
+		for search in [Search::AStar, Search::Dijkstra, Search::IdaStar] {
+			let (organization, stats) = burrow.organization_with(search);
+			let (cost, moves) = organization.unwrap();
+			assert_eq!((cost, moves.iter().map(|m| m.cost).sum()), (4008, 4008), "{search:?}");
+			assert!(stats.expanded > 0);
+			if let Search::IdaStar = search {
+				assert_eq!((stats.max_heap_len, stats.seen_len), (moves.len() + 1, 0));
+			}
+		}
+		Ok(())
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn keys_benchmark() {
+		use std::{mem::size_of, time::Instant};
+
+		let burrow = super::input_burrow_from_str(&super::unfolded(include_str!("day23.txt")));
+		let geometry = burrow.geometry.as_ref();
+		let amphipods_size = size_of::<BurrowAmphipods>() + burrow.amphipods.len() * size_of::<(usize, AmphipodKind)>();
+
+		let start = Instant::now();
+		let (vec_organization, vec_stats) = burrow.searched_by_key(Search::AStar, SearchStats::default(), Clone::clone);
+		println!("Vec keys: {:?}, {vec_stats:?}, ~{} KiB of seen keys", start.elapsed(), vec_stats.seen_len * amphipods_size / 1024);
+
+		let start = Instant::now();
+		let (packed_organization, packed_stats) = burrow.searched_by_key(Search::AStar, SearchStats::default(), |a| geometry.packed(a));
+		println!("Packed keys: {:?}, {packed_stats:?}, ~{} KiB of seen keys", start.elapsed(), packed_stats.seen_len * size_of::<PackedAmphipods>() / 1024);
+
+		assert_eq!(vec_organization.map(|(cost, _)| cost), packed_organization.map(|(cost, _)| cost));
+		assert!(packed_stats.seen_len <= vec_stats.seen_len);
+	}
+}
+
+mod replay {
+	use std::{fmt, io, iter, time::Duration};
+	use itertools::Itertools as _;
+	use super::{Burrow, Move};
+	#[cfg(test)]
+	use super::AmphipodKind;
+
+	#[allow(dead_code)]
+	impl Burrow {
+		fn with_move(&self, mov: &Move) -> Self {
+			let mut burrow = self.clone();
+			let amphipod = burrow.amphipods.iter_mut()
+				.find(|(space, _)| *space == mov.from)
+				.expect("no amphipod to move");
+			assert_eq!(amphipod.1, mov.kind);
+			amphipod.0 = mov.to;
+			burrow
+		}
+
+		/// Yields this burrow, followed by the burrow after each of `moves`.
+		pub(super) fn replay<'a>(&self, moves: &'a [Move]) -> impl Iterator<Item = Self> + 'a {
+			let mut burrow = Some(self.clone());
+			let mut moves = moves.iter();
+			iter::from_fn(move || {
+				let current = burrow.take()?;
+				burrow = moves.next().map(|mov| current.with_move(mov));
+				Some(current)
+			})
+		}
+
+		/// Writes each burrow of the replay, captioned by the move that led to it. With a
+		/// `frame_delay`, each frame is drawn over the previous one after sleeping that long
+		/// (using terminal escape codes); without one, frames follow each other as plain text.
+		pub(super) fn write_replay(&self, moves: &[Move], out: &mut impl io::Write, frame_delay: Option<Duration>) -> io::Result<()> {
+			let frame_lines = self.geometry.map.len() + 1;
+			// Only animated frames clear what’s left of the (longer) caption they’re drawn over
+			let clear_line = if frame_delay.is_some() { "\x1b[K" } else { "" };
+			let captions = iter::once(None).chain(moves.iter().map(Some));
+			for (i, (burrow, mov)) in self.replay(moves).zip(captions).enumerate() {
+				if let Some(frame_delay) = frame_delay {
+					if i > 0 {
+						std::thread::sleep(frame_delay);
+						write!(out, "\x1b[{frame_lines}A")?;
+					}
+				} else if i > 0 {
+					writeln!(out)?;
+				}
+				match mov {
+					Some(Move { kind, from, to, cost }) => writeln!(out, "{}: {from} → {to} ({cost}){clear_line}", self.geometry.kinds.letter(*kind))?,
+					None => writeln!(out, "Initial{clear_line}")?,
+				}
+				writeln!(out, "{burrow}")?;
+			}
+			out.flush()
+		}
+	}
+
+	impl fmt::Display for Burrow {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			let mut lines = self.geometry.map.iter()
+				.map(|line| line.chars().collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+			for &(space, kind) in &self.amphipods {
+				let [x, y] = self.geometry.locs[space];
+				lines[y][x] = self.geometry.kinds.letter(kind);
+			}
+			write!(f, "{}", lines.into_iter()
+				.map(|line| line.into_iter().collect::<String>())
+				.join("\n"))
+		}
+	}
+
+	#[test]
+	fn test() -> Result<(), super::parsing::BurrowError> {
+		let burrow = super::TEST_INPUT.parse::<Burrow>()?;
+		assert_eq!(burrow.to_string(), super::TEST_INPUT);
+
+		let (cost, moves) = burrow.organization().unwrap();
+		assert_eq!(cost, 12521);
+		assert_eq!(moves.iter().map(|m| m.cost).sum::<u64>(), cost);
+		assert_eq!(moves[0], Move { kind: AmphipodKind(1), from: 13, to: 3, cost: 40 });
+		assert_eq!(burrow.replay(&moves).count(), moves.len() + 1);
+		assert_eq!(burrow.replay(&moves).last().unwrap().to_string(), indoc::indoc! { "
+			#############
+			#...........#
+			###A#B#C#D###
+			  #A#B#C#D#
+			  #########
+		" }.trim_end());
+
+		let mut out = Vec::new();
+		burrow.write_replay(&moves[..1], &mut out, None).unwrap();
+		assert_eq!(String::from_utf8(out).unwrap().trim_end(), indoc::indoc! { "
+			Initial
+			#############
+			#...........#
+			###B#C#B#D###
+			  #A#D#C#A#
+			  #########
+
+			B: 13 → 3 (40)
+			#############
+			#...B.......#
+			###B#C#.#D###
+			  #A#D#C#A#
+			  #########
+		" }.trim_end());
+
+		let mut out = Vec::new();
+		burrow.write_replay(&moves[..1], &mut out, Some(Duration::ZERO)).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert!(out.starts_with("Initial\x1b[K\n"));
+		assert!(out.contains("\x1b[6AB: 13 → 3 (40)\x1b[K\n"));
+
+		assert_eq!(super::unfolded(super::TEST_INPUT).parse::<Burrow>()?.to_string(), indoc::indoc! { "
+			#############
+			#...........#
+			###B#C#B#D###
+			  #D#C#B#A#
+			  #D#B#A#C#
+			  #A#D#C#A#
+			  #########
+		" }.trim_end());
 		Ok(())
 	}
 }
 
 
-fn input_burrow_from_str<const NS: usize, const NA: usize>(s: &str) -> Burrow<NS, NA> {
+/// Inserts `BURROW_FOLD` below the first line of side rooms.
+fn unfolded(s: &str) -> String {
+	let mut lines = s.lines().collect::<Vec<_>>();
+	lines.splice(3..3, BURROW_FOLD.lines());
+	lines.join("\n")
+}
+
+fn input_burrow_from_str(s: &str) -> Burrow {
 	s.parse().unwrap()
 }
 
-fn input_burrow<const NS: usize, const NA: usize>() -> Burrow<NS, NA> {
-	input_burrow_from_str::<NS, NA>(include_str!("day23.txt"))
+fn input_burrow() -> Burrow {
+	input_burrow_from_str(include_str!("day23.txt"))
 }
 
 
-fn part1and2_impl<const NS: usize, const NA: usize>(input_burrow: Burrow<NS, NA>) -> u32 {
+fn part1and2_impl(input_burrow: Burrow) -> u64 {
 	input_burrow.organization_cost().unwrap()
 }
 
-pub(crate) fn part1() -> u32 {
-	const NS: usize = BURROW_FOLDED_NUM_SPACES;
-	const NA: usize = BURROW_FOLDED_NUM_AMPHIPODS;
-	part1and2_impl(input_burrow::<NS, NA>())
+pub(crate) fn part1() -> u64 {
+	part1and2_impl(input_burrow())
 }
 
 #[allow(dead_code)]
-pub(crate) fn part2() -> u32 {
-	const NS: usize = BURROW_UNFOLDED_NUM_SPACES;
-	const NA: usize = BURROW_UNFOLDED_NUM_AMPHIPODS;
-	part1and2_impl(input_burrow::<NS, NA>())
+pub(crate) fn part2() -> u64 {
+	part1and2_impl(input_burrow_from_str(&unfolded(include_str!("day23.txt"))))
 }
 
 
 mod parsing {
-	use std::{iter, str::FromStr};
-	use super::{
-		BURROW_FOLDED, BURROW_ALL_LOCS,
-		BURROW_SPACES_IN_FOLD, BURROW_AMPHIPOD_KINDS_IN_FOLD,
-		BURROW_UNFOLDED_NUM_AMPHIPODS,
-		AmphipodKind, Burrow};
+	use std::{collections::VecDeque, num::ParseIntError, rc::Rc, str::FromStr};
+	use itertools::Itertools as _;
+	use super::{AmphipodKind, AmphipodKinds, Burrow, BurrowGeometry};
 
 	#[allow(dead_code)]
 	#[derive(Debug)]
-	pub(crate) struct InvalidAmphipodKindError { found: char }
+	pub(super) enum AmphipodKindsError {
+		NoKinds,
+		InvalidFormat { kind: usize },
+		InvalidLetter { kind: usize, found: String },
+		DuplicateLetter { kind: usize, letter: char },
+		InvalidStepCost { kind: usize, source: ParseIntError },
+	}
 
-	impl TryFrom<char> for AmphipodKind {
-		type Error = InvalidAmphipodKindError;
-		fn try_from(value: char) -> Result<Self, Self::Error> {
-			use AmphipodKind::*;
-			match value {
-				'A' => Ok(Amber),
-				'B' => Ok(Bronze),
-				'C' => Ok(Copper),
-				'D' => Ok(Desert),
-				found => Err(InvalidAmphipodKindError { found })
+	/// Expects whitespace-separated kinds like `A=1 B=10`, in order of their target side rooms.
+	impl FromStr for AmphipodKinds {
+		type Err = AmphipodKindsError;
+		fn from_str(s: &str) -> Result<Self, Self::Err> {
+			use AmphipodKindsError::*;
+			let mut kinds = Vec::<(char, u32)>::new();
+			for (kind, part) in s.split_whitespace().enumerate() {
+				let (letter, step_cost) = part.split_once('=')
+					.ok_or(InvalidFormat { kind })?;
+				let letter = match letter.chars().exactly_one() {
+					Ok(letter) if letter.is_ascii_uppercase() => letter,
+					_ => return Err(InvalidLetter { kind, found: letter.to_owned() }),
+				};
+				if kinds.iter().any(|&(l, _)| l == letter) { return Err(DuplicateLetter { kind, letter }) }
+				let step_cost = step_cost.parse().map_err(|e| InvalidStepCost { kind, source: e })?;
+				kinds.push((letter, step_cost));
 			}
+			if kinds.is_empty() { return Err(NoKinds) }
+			Ok(AmphipodKinds(kinds))
 		}
 	}
 
+	#[allow(dead_code)]
+	#[derive(Debug)]
+	pub(crate) struct InvalidAmphipodKindError { found: char }
+
 	#[allow(dead_code)]
 	#[derive(Debug)]
 	pub(super) enum BurrowErrorKind {
-		InvalidFormat { found: Option<char> },
+		InvalidFormat { found: char },
 		InvalidAmphipod(InvalidAmphipodKindError),
 	}
 
 	#[allow(dead_code)]
 	#[derive(Debug)]
+	pub(super) enum GeometryError {
+		NoHallway,
+		/// A space that’s neither in the hallway, nor in a side room below it.
+		StraySpace { line: usize, column: usize },
+		InvalidRoomsCount(usize),
+		UnevenRooms { room: usize, depth: usize },
+	}
+
+	#[allow(dead_code, clippy::enum_variant_names)]
+	#[derive(Debug)]
 	pub(super) enum BurrowError {
 		InvalidLine { line: usize, column: usize, kind: BurrowErrorKind },
+		InvalidGeometry(GeometryError),
 		InvalidAmphipodsCount(AmphipodKind, usize),
 	}
 
-	impl<const NS: usize, const NA: usize> FromStr for Burrow<NS, NA> {
+	impl BurrowGeometry {
+		/// Expects `s` to only contain walls (`#`), nothing (` `) and open spaces (`.`).
+		fn from_str_with_kinds(s: &str, kinds: AmphipodKinds) -> Result<Self, GeometryError> {
+			use GeometryError::*;
+
+			let map = s.lines().map(str::to_owned).collect::<Vec<_>>();
+			let locs = map.iter().enumerate()
+				.flat_map(|(y, line)| line.bytes().positions(|b| b == b'.').map(move |x| [x, y]))
+				.collect::<Vec<_>>();
+			let space_at = |x: usize, y: usize| locs.iter().position(|&loc| loc == [x, y]);
+
+			// The hallway is the first run of spaces
+			let &[hallway_x, hallway_y] = locs.first().ok_or(NoHallway)?;
+			let hallway_len = Iterator::zip(locs.iter(), hallway_x..)
+				.take_while(|&(&[x, y], hallway_x)| y == hallway_y && x == hallway_x)
+				.count();
+
+			// Every other space must be below another space, and not next to one
+			let mut space_rooms = vec![None; locs.len()];
+			let mut rooms = Vec::<Vec<usize>>::new();
+			for (space, &[x, y]) in locs.iter().enumerate().skip(hallway_len) {
+				let above = y.checked_sub(1).and_then(|y| space_at(x, y));
+				let left = x.checked_sub(1).and_then(|x| space_at(x, y));
+				if y == hallway_y || above.is_none() || left.is_some() || space_at(x + 1, y).is_some() {
+					return Err(StraySpace { line: y + 1, column: x + 1 })
+				}
+				let room = space_rooms[above.unwrap()].unwrap_or_else(|| {
+					rooms.push(Vec::new());
+					rooms.len() - 1
+				});
+				space_rooms[space] = Some(room);
+				rooms[room].push(space);
+			}
+
+			if rooms.is_empty() || rooms.len() != kinds.len() { return Err(InvalidRoomsCount(rooms.len())) }
+			let depth = rooms[0].len();
+			if let Some((room, spaces)) = rooms.iter().enumerate().find(|(_, spaces)| spaces.len() != depth) {
+				return Err(UnevenRooms { room, depth: spaces.len() })
+			}
+
+			let steps = locs.iter()
+				.map(|&[x, y]| [
+					y.checked_sub(1).and_then(|y| space_at(x, y)),
+					space_at(x + 1, y),
+					space_at(x, y + 1),
+					x.checked_sub(1).and_then(|x| space_at(x, y)),
+				].into_iter().flatten().collect::<Vec<_>>())
+				.collect::<Vec<_>>();
+
+			let outside_side_room = (0..locs.len())
+				.map(|space| space_rooms[space].is_none()
+					&& steps[space].iter().any(|&s| space_rooms[s].is_some()))
+				.collect();
+
+			let step_counts = (0..locs.len())
+				.map(|from| {
+					let mut counts = vec![usize::MAX; locs.len()];
+					let mut queue = VecDeque::from([(from, 0)]);
+					while let Some((space, count)) = queue.pop_front() {
+						if counts[space] <= count { continue }
+						counts[space] = count;
+						queue.extend(steps[space].iter().map(|&s| (s, count + 1)));
+					}
+					counts
+				})
+				.collect();
+
+			Ok(BurrowGeometry {
+				rooms: Iterator::zip(kinds.iter(), rooms).collect(),
+				kinds,
+				map,
+				locs,
+				steps,
+				space_rooms,
+				outside_side_room,
+				step_counts,
+			})
+		}
+	}
+
+	impl FromStr for Burrow {
 		type Err = BurrowError;
 		fn from_str(s: &str) -> Result<Self, Self::Err> {
+			Burrow::from_str_with_kinds(s, AmphipodKinds::default())
+		}
+	}
+
+	impl Burrow {
+		pub(super) fn from_str_with_kinds(s: &str, kinds: AmphipodKinds) -> Result<Self, BurrowError> {
 			use BurrowErrorKind::*;
 			fn bur_line_err(l: usize, c: usize, kind: BurrowErrorKind) -> BurrowError {
 				BurrowError::InvalidLine { line: l + 1, column: c + 1, kind }
 			}
 
-			let mut lines = s.lines().enumerate();
-			let mut exp_lines = BURROW_FOLDED.lines().enumerate();
-			let mut amphipods = iter::from_fn(move || match (lines.next(), exp_lines.next()) {
-				(Some((l, line)), Some((_, exp_line))) => {
-					let mut chars = line.chars().enumerate();
-					let mut exp_chars = exp_line.chars().enumerate();
-					Some(itertools::Either::Left(iter::from_fn(move || match (chars.next(), exp_chars.next()) {
-						(Some((c, chr)), Some((_, 'a' | 'b' | 'c' | 'd'))) => {
-							Some(Some(chr.try_into()
-								.map(|amphipod| (
-									BURROW_ALL_LOCS.iter().position(|&[x, y]| x == c && y == l).unwrap(),
-									amphipod))
-								.map_err(|e| bur_line_err(l, c, InvalidAmphipod(e)))))
+			let mut map = String::with_capacity(s.len());
+			let mut amphipod_locs = Vec::new();
+			for (l, line) in s.lines().enumerate() {
+				for (c, chr) in line.chars().enumerate() {
+					match chr {
+						'#' | ' ' | '.' => map.push(chr),
+						chr if chr.is_ascii_uppercase() => {
+							let amphipod = kinds.find(chr)
+								.ok_or_else(|| bur_line_err(l, c, InvalidAmphipod(InvalidAmphipodKindError { found: chr })))?;
+							amphipod_locs.push(([c, l], amphipod));
+							map.push('.');
 						}
-						(Some((c, chr)), exp) => match exp {
-							Some((_, exp_chr)) if chr == exp_chr => Some(None),
-							_ => Some(Some(Err(bur_line_err(l, c, InvalidFormat { found: Some(chr) })))),
-						}
-						(None, Some((c, _))) => {
-							Some(Some(Err(bur_line_err(l, c, InvalidFormat { found: None }))))
-						}
-						(None, None) => None,
-					}).flatten()))
-				}
-				(Some((l, line)), _) => {
-					Some(itertools::Either::Right(iter::once(Err(bur_line_err(l, 0, InvalidFormat { found: line.chars().next() })))))
-				}
-				(None, Some((l, _))) => {
-					Some(itertools::Either::Right(iter::once(Err(bur_line_err(l, 0, InvalidFormat { found: None })))))
+						found => return Err(bur_line_err(l, c, InvalidFormat { found })),
+					}
 				}
-				(None, None) => None,
-			}).flatten().collect::<Result<Vec<_>, _>>()?;
-
-			for amphipod in [AmphipodKind::Amber, AmphipodKind::Bronze, AmphipodKind::Copper, AmphipodKind::Desert].into_iter() {
-				let count = amphipods.iter().filter(|(_, a)| *a == amphipod).count();
-				if count != 2 { return Err(BurrowError::InvalidAmphipodsCount(amphipod, count)) }
+				map.push('\n');
 			}
-			
-			if NA == BURROW_UNFOLDED_NUM_AMPHIPODS {
-				for (amphipod_space, _) in amphipods.iter_mut().skip(4) {
-					*amphipod_space += 8;
-				}
 
-				_ = amphipods
-					.splice(4..4, Iterator::zip(BURROW_SPACES_IN_FOLD, BURROW_AMPHIPOD_KINDS_IN_FOLD))
-					.collect::<Vec<_>>();
+			let geometry = BurrowGeometry::from_str_with_kinds(&map, kinds).map_err(BurrowError::InvalidGeometry)?;
+			let amphipods = amphipod_locs.into_iter()
+				.map(|(loc, amphipod)| (geometry.locs.iter().position(|&l| l == loc).unwrap(), amphipod))
+				.collect::<Vec<_>>();
+
+			for amphipod in geometry.kinds.iter() {
+				let count = amphipods.iter().filter(|(_, a)| *a == amphipod).count();
+				if count != geometry.room_depth() { return Err(BurrowError::InvalidAmphipodsCount(amphipod, count)) }
 			}
 
-			Ok(Burrow { amphipods: amphipods.try_into().unwrap() })
+			Ok(Burrow { geometry: Rc::new(geometry), amphipods })
 		}
 	}
 
 	#[test]
 	fn test() {
-		use {BurrowError::*, BurrowErrorKind::*};
-		const NSF: usize = super::BURROW_FOLDED_NUM_SPACES;
-		const NAF: usize = super::BURROW_FOLDED_NUM_AMPHIPODS;
-		assert!(matches!("".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 1, column: 1, kind: InvalidFormat { found: None } })));
-		assert!(matches!("#".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 1, column: 2, kind: InvalidFormat { found: None } })));
-		assert!(matches!("##x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 1, column: 3, kind: InvalidFormat { found: Some('x') } })));
-		assert!(matches!("##############x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 1, column: 14, kind: InvalidFormat { found: Some('#') } })));
-		assert!(matches!("#############".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 2, column: 1, kind: InvalidFormat { found: None } })));
-		assert!(matches!("#############\n#.".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 2, column: 3, kind: InvalidFormat { found: None } })));
-		assert!(matches!("#############\n#..x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 2, column: 4, kind: InvalidFormat { found: Some('x') } })));
-		assert!(matches!("#############\n#...........##x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 2, column: 14, kind: InvalidFormat { found: Some('#') } })));
-		assert!(matches!("#############\n#...........#\n".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 3, column: 1, kind: InvalidFormat { found: None } })));
-		assert!(matches!("#############\n#...........#\n#".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 3, column: 2, kind: InvalidFormat { found: None } })));
-		assert!(matches!("#############\n#...........#\n##x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 3, column: 3, kind: InvalidFormat { found: Some('x') } })));
-		assert!(matches!("#############\n#...........#\n###x".parse::<Burrow<NSF, NAF>>(), Err(InvalidLine { line: 3, column: 4, kind: InvalidAmphipod(_) })));
-		assert!(matches!(super::TEST_INPUT.replace('A', "B").parse::<Burrow<NSF, NAF>>(), Err(BurrowError::InvalidAmphipodsCount(AmphipodKind::Amber, 0))));
-		assert!(matches!(super::TEST_INPUT.parse::<Burrow<NSF, NAF>>(), Ok(_)));
-		const NSU: usize = super::BURROW_UNFOLDED_NUM_SPACES;
-		const NAU: usize = super::BURROW_UNFOLDED_NUM_AMPHIPODS;
-		assert!(matches!(super::TEST_INPUT.parse::<Burrow<NSU, NAU>>(), Ok(_)));
+		use {BurrowError::*, BurrowErrorKind::*, GeometryError::*};
+		assert!(matches!("".parse::<Burrow>(), Err(InvalidGeometry(NoHallway))));
+		assert!(matches!("##x".parse::<Burrow>(), Err(InvalidLine { line: 1, column: 3, kind: InvalidFormat { found: 'x' } })));
+		assert!(matches!("#############\n#..x".parse::<Burrow>(), Err(InvalidLine { line: 2, column: 4, kind: InvalidFormat { found: 'x' } })));
+		assert!(matches!("#############\n#...........#\n###E".parse::<Burrow>(), Err(InvalidLine { line: 3, column: 4, kind: InvalidAmphipod(_) })));
+		assert!(matches!("#############\n#.....#.....#".parse::<Burrow>(), Err(InvalidGeometry(StraySpace { line: 2, column: 8 }))));
+		assert!(matches!("#############\n#...........#\n###A#B#C#D###\n  #A#B#C#D#.".parse::<Burrow>(), Err(InvalidGeometry(StraySpace { line: 4, column: 12 }))));
+		assert!(matches!(".....\n.".parse::<Burrow>(), Err(InvalidGeometry(InvalidRoomsCount(1)))));
+		assert!(matches!(".....\n..".parse::<Burrow>(), Err(InvalidGeometry(StraySpace { line: 2, column: 1 }))));
+		assert!(matches!("...#.".parse::<Burrow>(), Err(InvalidGeometry(StraySpace { line: 1, column: 5 }))));
+		assert!(matches!("#############\n#...........#\n###A#B#C#D###\n  #A#B#C..#".parse::<Burrow>(), Err(InvalidGeometry(StraySpace { line: 4, column: 8 }))));
+		assert!(matches!("#############\n#...........#\n###A#B#C#D###\n  #A#B#C#D#\n  #A#######".parse::<Burrow>(), Err(InvalidGeometry(UnevenRooms { room: 1, depth: 2 }))));
+		assert!(format!("#{}#\n###A#B#C#D###", ".".repeat(40)).parse::<Burrow>().is_ok());
+		assert!(matches!("#############\n#...........#\n###A#B#C#####".parse::<Burrow>(), Err(InvalidGeometry(InvalidRoomsCount(3)))));
+		assert!(matches!(super::TEST_INPUT.replace('A', "B").parse::<Burrow>(), Err(InvalidAmphipodsCount(AmphipodKind(0), 0))));
+		assert!(super::TEST_INPUT.parse::<Burrow>().is_ok());
+		assert!(super::unfolded(super::TEST_INPUT).parse::<Burrow>().is_ok());
+
+		assert!(matches!("A=1 B".parse::<AmphipodKinds>(), Err(AmphipodKindsError::InvalidFormat { kind: 1 })));
+		assert!(matches!("A=1 b=2".parse::<AmphipodKinds>(), Err(AmphipodKindsError::InvalidLetter { kind: 1, found }) if found == "b"));
+		assert!(matches!("A=1 AB=2".parse::<AmphipodKinds>(), Err(AmphipodKindsError::InvalidLetter { kind: 1, found }) if found == "AB"));
+		assert!(matches!("A=1 A=2".parse::<AmphipodKinds>(), Err(AmphipodKindsError::DuplicateLetter { kind: 1, letter: 'A' })));
+		assert!(matches!("A=1 B=x".parse::<AmphipodKinds>(), Err(AmphipodKindsError::InvalidStepCost { kind: 1, .. })));
+		assert!(matches!(" ".parse::<AmphipodKinds>(), Err(AmphipodKindsError::NoKinds)));
+		assert!(matches!(Burrow::from_str_with_kinds("#...#", AmphipodKinds(vec![])), Err(InvalidGeometry(InvalidRoomsCount(0)))));
+		assert!(matches!("A=1 B=10 C=100 D=1000".parse::<AmphipodKinds>(), Ok(AmphipodKinds(kinds)) if kinds == AmphipodKinds::default().0));
+		let kinds = "A=1 B=2 C=3 D=4 E=5 F=6".parse::<AmphipodKinds>().unwrap();
+		assert!(matches!(Burrow::from_str_with_kinds(super::TEST_INPUT, kinds.clone()), Err(InvalidGeometry(InvalidRoomsCount(4)))));
+		assert!(matches!(Burrow::from_str_with_kinds("#####\n#...#\n##E##", kinds), Err(InvalidGeometry(InvalidRoomsCount(1)))));
+
+		let burrow = "###########\n#.........#\n##A#B#C#D##".parse::<Burrow>().unwrap();
+		assert_eq!((burrow.geometry.rooms.len(), burrow.geometry.room_depth()), (4, 1));
+		itertools::assert_equal(burrow.geometry.outside_side_room.iter().positions(|&o| o), [1, 3, 5, 7]);
 	}
 }
 
@@ -604,12 +1015,97 @@ const TEST_INPUT: &str = indoc::indoc! { "
 
 #[test]
 fn tests() {
-	const NSF: usize = BURROW_FOLDED_NUM_SPACES;
-	const NAF: usize = BURROW_FOLDED_NUM_AMPHIPODS;
-	assert_eq!(part1and2_impl(input_burrow_from_str::<NSF, NAF>(TEST_INPUT)), 12521);
+	assert_eq!(part1and2_impl(input_burrow_from_str(TEST_INPUT)), 12521);
 	assert_eq!(part1(), 13066);
-	const NSU: usize = BURROW_UNFOLDED_NUM_SPACES;
-	const NAU: usize = BURROW_UNFOLDED_NUM_AMPHIPODS;
-	assert_eq!(part1and2_impl(input_burrow_from_str::<NSU, NAU>(TEST_INPUT)), 44169);
+	assert_eq!(part1and2_impl(input_burrow_from_str(&unfolded(TEST_INPUT))), 44169);
 	assert_eq!(part2(), 47328);
+
+	const INPUT_SHALLOW: &str = indoc::indoc! { "
+		#############
+		#...........#
+		###B#A#C#D###
+		  #########
+	" };
+	assert_eq!(part1and2_impl(input_burrow_from_str(INPUT_SHALLOW)), 46);
+
+	const INPUT_DEEP_NARROW: &str = indoc::indoc! { "
+		###########
+		#.........#
+		##B#C#B#D##
+		 #D#C#B#A#
+		 #A#B#A#C#
+		 #A#D#C#D#
+		 #########
+	" };
+	let burrow = input_burrow_from_str(INPUT_DEEP_NARROW);
+	let (cost, moves) = burrow.organization().unwrap();
+	assert_eq!(moves.iter().map(|m| m.cost).sum::<u64>(), cost);
+	assert_eq!(burrow.replay(&moves).last().unwrap().to_string(), indoc::indoc! { "
+		###########
+		#.........#
+		##A#B#C#D##
+		 #A#B#C#D#
+		 #A#B#C#D#
+		 #A#B#C#D#
+		 #########
+	" }.trim_end());
+
+	// Step costs whose totals don’t fit in 32 bits
+	for (kinds, cost) in [
+		("A=1 B=10 C=100 D=2000000000", 12 * 2000000000 + 521),
+		("A=4294967295 B=4294967295 C=4294967295 D=4294967295", 38 * u32::MAX as u64),
+	] {
+		let burrow = Burrow::from_str_with_kinds(TEST_INPUT, kinds.parse().unwrap()).unwrap();
+		assert_eq!(burrow.organization_cost(), Some(cost));
+		assert_eq!(burrow.organization_with(organization::Search::Dijkstra).0.map(|(cost, _)| cost), Some(cost));
+	}
+
+	// Too many spaces to pack into 128 bits
+	const INPUT_DEEPER: &str = indoc::indoc! { "
+		#############
+		#...........#
+		###B#A#C#D###
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #A#B#C#D#
+		  #########
+	" };
+	assert_eq!(part1and2_impl(input_burrow_from_str(INPUT_DEEPER)), 46);
+
+	// Deep side rooms (the heuristic must not grow factorially with the depth)
+	const INPUT_DEEP_TWO_KINDS: &str = indoc::indoc! { "
+		#############
+		#...........#
+		###B#A#######
+		  #A#B#
+		  #A#B#
+		  #B#A#
+		  #A#B#
+		  #B#A#
+		  #####
+	" };
+	let burrow = Burrow::from_str_with_kinds(INPUT_DEEP_TWO_KINDS, "A=1 B=10".parse().unwrap()).unwrap();
+	let (a_star, _) = burrow.organization_with(organization::Search::AStar);
+	let (dijkstra, _) = burrow.organization_with(organization::Search::Dijkstra);
+	assert_eq!(a_star.map(|(cost, _)| cost), Some(686));
+	assert_eq!(dijkstra.map(|(cost, _)| cost), Some(686));
+
+	const INPUT_SIX_KINDS: &str = indoc::indoc! { "
+		#################
+		#...............#
+		###B#A#C#D#F#E###
+		  ###########
+	" };
+	let burrow = Burrow::from_str_with_kinds(INPUT_SIX_KINDS, "A=1 B=2 C=3 D=4 E=5 F=6".parse().unwrap()).unwrap();
+	assert_eq!(burrow.organization_cost(), Some(68));
+	assert_eq!(burrow.replay(&burrow.organization().unwrap().1).last().unwrap().to_string(), indoc::indoc! { "
+		#################
+		#...............#
+		###A#B#C#D#E#F###
+		  ###########
+	" }.trim_end());
 }
@@ -1,7 +1,7 @@
 // Copyright (c) 2022 Bastiaan Marinus van de Weerd
 
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 enum SeaCucumber { East, South }
 
 struct Grid {
@@ -9,13 +9,44 @@ struct Grid {
 	width: usize,
 }
 
+/// Like `Grid`, but with a bitmask per row for each herd (each row starting at a new word).
+struct BitGrid {
+	width: usize,
+	height: usize,
+	east: Vec<u64>,
+	south: Vec<u64>,
+}
+
+/// The number of sea cucumbers of each herd that moved during a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct StepMoves {
+	east: usize,
+	south: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SimulationEnd {
+	/// Nothing moved during `step` (1-based).
+	Stuck { step: usize },
+	/// The state after `start` steps recurs every `length` (more than 1) steps.
+	Cycle { start: usize, length: usize },
+}
+
+struct Simulation {
+	/// Per step (until the end), the moves during it.
+	#[allow(dead_code)]
+	moves: Vec<StepMoves>,
+	end: SimulationEnd,
+}
+
 
 mod simulation {
-	use super::{SeaCucumber, Grid};
+	use std::{collections::{HashMap, hash_map::Entry}, fmt::Display};
+	use super::{SeaCucumber, Grid, StepMoves, Simulation, SimulationEnd};
 
 	impl Grid {
-		fn tick_east(&mut self) -> bool {
-			let mut any_moved = false;
+		fn tick_east(&mut self) -> usize {
+			let mut moved = 0;
 			for y in 0..self.spaces.len() / self.width {
 				let iy = y * self.width;
 				let first_none = self.spaces[iy].is_none();
@@ -27,7 +58,7 @@ mod simulation {
 						// println!("Moving East: {i0}:({x0},{y}) -> {i1}:({x1},{y})");
 						self.spaces.swap(i0, i1);
 						just_moved_x = x1;
-						any_moved = true;
+						moved += 1;
 					}
 				}
 				let last_x = self.width - 1;
@@ -36,15 +67,15 @@ mod simulation {
 					if let Some(SeaCucumber::East) = self.spaces[last_i] {
 						// println!("Moving East: {last_i}:({last_x},{y}) -> {iy}:(0,{y}) (wrapped)");
 						self.spaces.swap(last_i, iy);
-						any_moved = true
+						moved += 1;
 					}
 				}
 			}
-			any_moved
+			moved
 		}
 
-		fn tick_south(&mut self) -> bool {
-			let mut any_moved = false;
+		fn tick_south(&mut self) -> usize {
+			let mut moved = 0;
 			let height = self.spaces.len() / self.width;
 			for x in 0..self.width {
 				let first_none = self.spaces[x].is_none();
@@ -56,7 +87,7 @@ mod simulation {
 						// println!("Moving South: {i0}:({x},{y0}) -> {i1}:({x},{y1})");
 						self.spaces.swap(i0, i1);
 						just_moved_y = y1;
-						any_moved = true;
+						moved += 1;
 					}
 				}
 				let last_y = height - 1;
@@ -65,30 +96,52 @@ mod simulation {
 					if let Some(SeaCucumber::South) = self.spaces[last_i] {
 						// println!("Moving South: {last_i}:({x},{last_y}) -> {x}:({x},0) (wrapped)");
 						self.spaces.swap(last_i, x);
-						any_moved = true
+						moved += 1;
 					}
 				}
 			}
-			any_moved
+			moved
 		}
 
-		fn tick(&mut self) -> bool {
-			let any_moved_east = self.tick_east();
-			let any_moved_south = self.tick_south();
-			any_moved_east || any_moved_south
+		pub(super) fn tick(&mut self) -> StepMoves {
+			let east = self.tick_east();
+			let south = self.tick_south();
+			StepMoves { east, south }
 		}
 
-		pub(super) fn tick_until_stuck(&mut self) -> usize {
-			for i in 0.. {
-				if !self.tick() { return i + 1 }
+		/// Ticks until nothing moves anymore, or until a state recurs (which would then do so forever).
+		pub(super) fn simulate(&mut self) -> Simulation {
+			let mut seen = HashMap::from([(self.spaces.clone(), 0)]);
+			let mut moves = Vec::new();
+			loop {
+				let step_moves = self.tick();
+				moves.push(step_moves);
+				if step_moves == (StepMoves { east: 0, south: 0 }) {
+					return Simulation { end: SimulationEnd::Stuck { step: moves.len() }, moves }
+				}
+				match seen.entry(self.spaces.clone()) {
+					Entry::Occupied(entry) => {
+						let start = *entry.get();
+						return Simulation { end: SimulationEnd::Cycle { start, length: moves.len() - start }, moves }
+					}
+					Entry::Vacant(entry) => { entry.insert(moves.len()); }
+				}
 			}
-			unreachable!()
 		}
 	}
 
 
+	impl Display for SimulationEnd {
+		fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+			match self {
+				SimulationEnd::Stuck { step } => write!(f, "stuck at step {step}"),
+				SimulationEnd::Cycle { start, length } => write!(f, "cycle of length {length} starting at step {start}"),
+			}
+		}
+	}
+
 	#[cfg(test)]
-	use std::fmt::{Display, Write};
+	use std::fmt::Write;
 
 	#[cfg(test)]
 	impl Display for Grid {
@@ -109,13 +162,197 @@ mod simulation {
 }
 
 
+#[allow(dead_code)]
+mod bit_parallel {
+	#[cfg(test)]
+	use rand::Rng;
+	use super::{SeaCucumber, Grid, BitGrid, StepMoves};
+
+	impl From<&Grid> for BitGrid {
+		fn from(grid: &Grid) -> Self {
+			let (width, height) = (grid.width, grid.spaces.len() / grid.width);
+			let stride = width.div_ceil(64);
+			let (mut east, mut south) = (vec![0; stride * height], vec![0; stride * height]);
+			for (i, space) in grid.spaces.iter().enumerate() {
+				let (x, y) = (i % width, i / width);
+				let herd = match space {
+					Some(SeaCucumber::East) => &mut east,
+					Some(SeaCucumber::South) => &mut south,
+					None => continue,
+				};
+				herd[y * stride + x / 64] |= 1 << (x % 64);
+			}
+			BitGrid { width, height, east, south }
+		}
+	}
+
+	impl From<&BitGrid> for Grid {
+		fn from(grid: &BitGrid) -> Self {
+			let stride = grid.stride();
+			let spaces = (0..grid.height)
+				.flat_map(|y| (0..grid.width).map(move |x| (y * stride + x / 64, 1 << (x % 64))))
+				.map(|(w, bit)| match (grid.east[w] & bit != 0, grid.south[w] & bit != 0) {
+					(true, _) => Some(SeaCucumber::East),
+					(_, true) => Some(SeaCucumber::South),
+					_ => None,
+				})
+				.collect();
+			Grid { spaces, width: grid.width }
+		}
+	}
+
+	/// Rotates the bits of a row (of `width` bits, padded with zeroes) one place
+	/// towards higher `x` (i.e. east), wrapping around at `width`.
+	fn rotate_east(row: &[u64], width: usize, rotated: &mut [u64]) {
+		let mut carry = row[(width - 1) / 64] >> ((width - 1) % 64) & 1;
+		for (r, &w) in rotated.iter_mut().zip(row) {
+			*r = w << 1 | carry;
+			carry = w >> 63;
+		}
+		rotated[(width - 1) / 64] &= last_word_mask(width);
+	}
+
+	/// Rotates the bits of a row (of `width` bits, padded with zeroes) one place
+	/// towards lower `x` (i.e. west), wrapping around at `width`.
+	fn rotate_west(row: &[u64], width: usize, rotated: &mut [u64]) {
+		let mut carry = 0;
+		for (r, &w) in rotated.iter_mut().zip(row).rev() {
+			*r = w >> 1 | carry << 63;
+			carry = w & 1;
+		}
+		rotated[(width - 1) / 64] |= carry << ((width - 1) % 64);
+	}
+
+	fn last_word_mask(width: usize) -> u64 {
+		match width % 64 {
+			0 => u64::MAX,
+			bits => (1 << bits) - 1,
+		}
+	}
+
+	impl BitGrid {
+		fn stride(&self) -> usize {
+			self.width.div_ceil(64)
+		}
+
+		fn tick_east(&mut self) -> usize {
+			let (width, stride) = (self.width, self.stride());
+			let mut moved = 0;
+			let (mut buf0, mut buf1) = (vec![0; stride], vec![0; stride]);
+			for (east, south) in Iterator::zip(self.east.chunks_mut(stride), self.south.chunks(stride)) {
+				for (e, (&ee, &s)) in buf0.iter_mut().zip(east.iter().zip(south)) {
+					*e = !(ee | s);
+				}
+				buf0[stride - 1] &= last_word_mask(width);
+
+				// Those with an empty space to the east of them move into it
+				rotate_west(&buf0, width, &mut buf1);
+				for (m, &e) in buf1.iter_mut().zip(east.iter()) {
+					*m &= e;
+					moved += m.count_ones() as usize;
+				}
+				rotate_east(&buf1, width, &mut buf0);
+				for ((e, &m), &moved_in) in east.iter_mut().zip(&buf1).zip(&buf0) {
+					*e = *e & !m | moved_in;
+				}
+			}
+			moved
+		}
+
+		fn tick_south(&mut self) -> usize {
+			let stride = self.stride();
+			let occupied = Iterator::zip(self.east.iter(), &self.south).map(|(e, s)| e | s).collect::<Vec<_>>();
+
+			// Those with an empty space to the south of them (wrapping around) move into it
+			let movers = (0..self.south.len())
+				.map(|i| self.south[i] & !occupied[(i + stride) % occupied.len()])
+				.collect::<Vec<_>>();
+			for (i, south) in self.south.iter_mut().enumerate() {
+				*south = *south & !movers[i] | movers[(i + movers.len() - stride) % movers.len()];
+			}
+			movers.iter().map(|m| m.count_ones() as usize).sum()
+		}
+
+		pub(super) fn tick(&mut self) -> StepMoves {
+			let east = self.tick_east();
+			let south = self.tick_south();
+			StepMoves { east, south }
+		}
+
+		pub(super) fn tick_until_stuck(&mut self) -> usize {
+			for i in 0.. {
+				if self.tick() == (StepMoves { east: 0, south: 0 }) { return i + 1 }
+			}
+			unreachable!()
+		}
+	}
+
+
+	#[cfg(test)]
+	fn random_grid(rng: &mut impl rand::Rng, width: usize, height: usize) -> Grid {
+		let spaces = (0..width * height)
+			.map(|_| match rng.gen_range(0..3) {
+				0 => Some(SeaCucumber::East),
+				1 => Some(SeaCucumber::South),
+				_ => None,
+			})
+			.collect();
+		Grid { spaces, width }
+	}
+
+	#[test]
+	fn test() {
+		let mut bit_grid = BitGrid::from(&super::input_grid_from_str(super::TEST_INPUT));
+		assert_eq!(bit_grid.tick(), StepMoves { east: 10, south: 14 });
+		assert_eq!(bit_grid.tick_until_stuck(), 57);
+		assert_eq!(BitGrid::from(&super::input_grid_from_str(include_str!("day25.txt"))).tick_until_stuck(), 419);
+
+		let mut rng = rand::thread_rng();
+		let word_edge_widths = [1, 2, 63, 64, 65, 127, 128, 129];
+		let random_widths = (0..200).map(|_| rng.gen_range(1..=200)).collect::<Vec<_>>();
+		for width in word_edge_widths.into_iter().chain(random_widths) {
+			let height = rng.gen_range(1..=20);
+			let mut grid = random_grid(&mut rng, width, height);
+			let mut bit_grid = BitGrid::from(&grid);
+			assert_eq!(Grid::from(&bit_grid).to_string(), grid.to_string());
+			for _ in 0..20 {
+				assert_eq!(bit_grid.tick(), grid.tick(), "{width}×{height}");
+				assert_eq!(Grid::from(&bit_grid).to_string(), grid.to_string());
+			}
+		}
+	}
+
+	#[test]
+	#[ignore = "benchmark"]
+	fn tick_benchmark() {
+		let mut rng = rand::thread_rng();
+		for size in [100, 1000] {
+			let mut grid = random_grid(&mut rng, size, size);
+			let mut bit_grid = BitGrid::from(&grid);
+
+			let start = std::time::Instant::now();
+			let moves = (0..100).map(|_| grid.tick()).collect::<Vec<_>>();
+			println!("{size}×{size}, 100 ticks, `Grid`: {:?}", start.elapsed());
+
+			let start = std::time::Instant::now();
+			let bit_moves = (0..100).map(|_| bit_grid.tick()).collect::<Vec<_>>();
+			println!("{size}×{size}, 100 ticks, `BitGrid`: {:?}", start.elapsed());
+
+			assert_eq!(moves, bit_moves);
+		}
+	}
+}
+
 fn input_grid_from_str(s: &str) -> Grid {
 	s.parse().unwrap()
 }
 
 
 fn part1_impl(mut input_grid: Grid) -> usize {
-	input_grid.tick_until_stuck()
+	match input_grid.simulate().end {
+		SimulationEnd::Stuck { step } => step,
+		end => panic!("never stuck: {end}"),
+	}
 }
 
 pub(crate) fn part1() -> usize {
@@ -220,4 +457,16 @@ const TEST_INPUT: &str = indoc::indoc! { "
 fn tests() {
 	assert_eq!(part1_impl(input_grid_from_str(TEST_INPUT)), 58);
 	assert_eq!(part1(), 419);
+
+	let simulation = input_grid_from_str(TEST_INPUT).simulate();
+	assert_eq!(simulation.moves.len(), 58);
+	assert_eq!(&simulation.moves[..2], [StepMoves { east: 10, south: 14 }, StepMoves { east: 9, south: 15 }]);
+	assert_eq!(simulation.moves[57], StepMoves { east: 0, south: 0 });
+
+	// A lone empty space makes the sea cucumbers around it go round in circles
+	let simulation = input_grid_from_str(">.>\n.vv\n").simulate();
+	assert_eq!(simulation.end.to_string(), "cycle of length 5 starting at step 0");
+	let simulation = input_grid_from_str(">..\nv>.\n..v\n").simulate();
+	assert_eq!(simulation.end, SimulationEnd::Cycle { start: 2, length: 3 });
+	assert_eq!(simulation.moves.iter().map(|m| (m.east, m.south)).collect::<Vec<_>>(), [(2, 2), (1, 2), (2, 2), (2, 2), (2, 2)]);
 }